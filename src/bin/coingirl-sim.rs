@@ -0,0 +1,354 @@
+//! Plays lots of solo games with the bot, without a window, and prints numbers to balance the
+//! coin set and speed curve with.
+//!
+//! ```sh
+//! cargo run --release --bin coingirl-sim -- --games 2000 --depth 1
+//! ```
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::Arc;
+use std::time::Duration;
+
+use coingirlgame_devcaders::board::{
+  random_row, BoardEvent, BoardState, BOARD_DIM, HEIGHT_RANGE, WIDTH_RANGE,
+};
+use coingirlgame_devcaders::bot::best_move;
+use coingirlgame_devcaders::coins::{CoinSet, ObjType};
+use coingirlgame_devcaders::curve::SOLO_SPEED;
+use coingirlgame_devcaders::scoring::{erase_points, merge_points};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const USAGE: &str = "usage: coingirl-sim [--games N] [--seed N] [--depth N] [--reaction SECS] \
+[--width N] [--height N] [--max-minutes N] [--coins PATH]";
+
+struct Options {
+  games: u64,
+  seed: u64,
+  depth: usize,
+  reaction_secs: f32,
+  width: i32,
+  height: i32,
+  max_minutes: f32,
+  coins: PathBuf,
+}
+
+impl Options {
+  fn parse() -> Result<Self, String> {
+    let mut options = Options {
+      games: 1000,
+      seed: 0,
+      depth: 1,
+      reaction_secs: 0.4,
+      width: BOARD_DIM.0,
+      height: BOARD_DIM.1,
+      max_minutes: 30.0,
+      coins: CoinSet::default_path(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+      let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+      let bad_value = || format!("invalid value for {flag}: {value:?}");
+      match flag.as_str() {
+        "--games" => options.games = value.parse().map_err(|_| bad_value())?,
+        "--seed" => options.seed = value.parse().map_err(|_| bad_value())?,
+        "--depth" => options.depth = value.parse().map_err(|_| bad_value())?,
+        "--reaction" => options.reaction_secs = value.parse().map_err(|_| bad_value())?,
+        "--width" => options.width = value.parse().map_err(|_| bad_value())?,
+        "--height" => options.height = value.parse().map_err(|_| bad_value())?,
+        "--max-minutes" => options.max_minutes = value.parse().map_err(|_| bad_value())?,
+        "--coins" => options.coins = PathBuf::from(value),
+        _ => return Err(format!("unknown flag {flag}")),
+      }
+    }
+    if options.games == 0 || options.depth == 0 || options.reaction_secs <= 0.0 {
+      return Err("--games, --depth and --reaction need to be above 0".to_string());
+    }
+    if !WIDTH_RANGE.contains(&options.width) || !HEIGHT_RANGE.contains(&options.height) {
+      return Err(format!(
+        "board {}x{} is out of range, --width goes from {} to {} and --height from {} to {}",
+        options.width,
+        options.height,
+        WIDTH_RANGE.start(),
+        WIDTH_RANGE.end(),
+        HEIGHT_RANGE.start(),
+        HEIGHT_RANGE.end()
+      ));
+    }
+    Ok(options)
+  }
+}
+
+/// Totals over any number of games.
+#[derive(Default)]
+struct Stats {
+  games: u64,
+  timed_out: u64,
+  survival: Duration,
+  shortest: Option<Duration>,
+  longest: Duration,
+  score: u64,
+  merges: u64,
+  /// Longest chain set off by each push, counting the first merge as 1.
+  chains: BTreeMap<u32, u64>,
+  from_rows: HashMap<ObjType, u64>,
+  from_merges: HashMap<ObjType, u64>,
+  from_rank_ups: HashMap<ObjType, u64>,
+}
+
+impl Stats {
+  fn add(&mut self, other: Stats) {
+    self.games += other.games;
+    self.timed_out += other.timed_out;
+    self.survival += other.survival;
+    self.shortest = match (self.shortest, other.shortest) {
+      (Some(a), Some(b)) => Some(a.min(b)),
+      (a, b) => a.or(b),
+    };
+    self.longest = self.longest.max(other.longest);
+    self.score += other.score;
+    self.merges += other.merges;
+    for (chain, count) in other.chains {
+      *self.chains.entry(chain).or_default() += count;
+    }
+    for (obj_type, count) in other.from_rows {
+      *self.from_rows.entry(obj_type).or_default() += count;
+    }
+    for (obj_type, count) in other.from_merges {
+      *self.from_merges.entry(obj_type).or_default() += count;
+    }
+    for (obj_type, count) in other.from_rank_ups {
+      *self.from_rank_ups.entry(obj_type).or_default() += count;
+    }
+  }
+
+  fn add_row(&mut self, row: &[ObjType]) {
+    for &obj_type in row {
+      *self.from_rows.entry(obj_type).or_default() += 1;
+    }
+  }
+
+  /// Tallies a settle and returns the points it scored.
+  fn add_events(&mut self, coin_set: &CoinSet, events: &[BoardEvent]) -> u32 {
+    let mut points = 0;
+    let mut longest_chain = None;
+    for event in events {
+      match event {
+        BoardEvent::Merged {
+          obj_type,
+          coins,
+          upgrade,
+          chain,
+        } => {
+          self.merges += 1;
+          points += merge_points(coin_set, *obj_type, coins.len(), *chain);
+          if let Some((coin, _)) = upgrade {
+            *self.from_merges.entry(coin.obj_type).or_default() += 1;
+          }
+          longest_chain = longest_chain.max(Some(*chain));
+        }
+        BoardEvent::Erased {
+          obj_type,
+          coins,
+          chain,
+          ..
+        } => {
+          points += erase_points(coin_set, *obj_type, coins.len(), *chain);
+          longest_chain = longest_chain.max(Some(*chain));
+        }
+        BoardEvent::RankedUp {
+          promoted, chain, ..
+        } => {
+          for (new_type, _) in promoted {
+            *self.from_rank_ups.entry(*new_type).or_default() += 1;
+          }
+          longest_chain = longest_chain.max(Some(*chain));
+        }
+        _ => (),
+      }
+    }
+    if let Some(chain) = longest_chain {
+      *self.chains.entry(chain + 1).or_default() += 1;
+    }
+    points
+  }
+}
+
+/// What the bot does with its next reaction.
+enum Step {
+  Walk(i32),
+  Pull,
+  Push,
+}
+
+/// Plays one solo game with the same row timing as the real thing. The bot takes one step per
+/// reaction, and working out a move takes one too.
+fn play(seed: u64, coin_set: &Arc<CoinSet>, options: &Options) -> Stats {
+  let mut stats = Stats {
+    games: 1,
+    ..Stats::default()
+  };
+  let mut rng = StdRng::seed_from_u64(seed);
+  let mut board = BoardState::new(options.width, options.height, coin_set.clone());
+  for _ in 0..3 {
+    let row = random_row(&mut rng, coin_set, options.width);
+    stats.add_row(&row);
+    board.insert_row(&row);
+  }
+
+  let curve = SOLO_SPEED;
+  let reaction = Duration::from_secs_f32(options.reaction_secs);
+  let max_time = Duration::from_secs_f32(options.max_minutes * 60.0);
+  let mut elapsed = Duration::ZERO;
+  let mut score = 0;
+  let mut next_row = curve.interval(0);
+  let mut next_step = reaction;
+  let mut col = options.width / 2;
+  let mut plan = VecDeque::new();
+  while !board.is_lost() {
+    if elapsed >= max_time {
+      stats.timed_out += 1;
+      break;
+    }
+    if next_step < next_row {
+      elapsed = next_step;
+      next_step += reaction;
+      // a walk that's already there takes no time
+      while matches!(plan.front(), Some(&Step::Walk(target)) if target == col) {
+        plan.pop_front();
+      }
+      let Some(step) = plan.pop_front() else {
+        if let Some((best, _)) = best_move(&board, options.depth) {
+          plan.push_back(Step::Walk(best.pull));
+          plan.push_back(Step::Pull);
+          plan.push_back(Step::Walk(best.push));
+          plan.push_back(Step::Push);
+        }
+        continue;
+      };
+      match step {
+        Step::Walk(target) => {
+          col += (target - col).signum();
+          if col != target {
+            plan.push_front(Step::Walk(target));
+          }
+        }
+        Step::Pull => {
+          board.pull(col);
+        }
+        Step::Push => {
+          board.push(col);
+          let events = board.settle();
+          score += stats.add_events(coin_set, &events);
+        }
+      }
+    } else {
+      elapsed = next_row;
+      let row = random_row(&mut rng, coin_set, options.width);
+      stats.add_row(&row);
+      board.insert_row(&row);
+      let events = board.settle();
+      score += stats.add_events(coin_set, &events);
+      next_row = elapsed + curve.interval(curve.level(elapsed, score));
+    }
+  }
+  stats.survival = elapsed;
+  stats.shortest = Some(elapsed);
+  stats.longest = elapsed;
+  stats.score = score as u64;
+  stats
+}
+
+fn format_duration(duration: Duration) -> String {
+  let secs = duration.as_secs_f64();
+  format!("{}m {:04.1}s", (secs / 60.0) as u64, secs % 60.0)
+}
+
+fn print_report(stats: &Stats, coin_set: &CoinSet, options: &Options) {
+  let games = stats.games as f64;
+  let minutes = stats.survival.as_secs_f64() / 60.0;
+  println!(
+    "{} games on a {}x{} board, seeds {}..{}, depth {}, {:.2}s reaction",
+    stats.games,
+    options.width,
+    options.height,
+    options.seed,
+    options.seed + options.games,
+    options.depth,
+    options.reaction_secs
+  );
+  println!();
+  println!(
+    "survival: {} on average, {} shortest, {} longest",
+    format_duration(stats.survival.div_f64(games)),
+    format_duration(stats.shortest.unwrap_or_default()),
+    format_duration(stats.longest)
+  );
+  if stats.timed_out > 0 {
+    println!(
+      "  {} games were stopped at the {} minute limit",
+      stats.timed_out, options.max_minutes
+    );
+  }
+  println!("score: {:.0} on average", stats.score as f64 / games);
+  println!("merges per minute: {:.2}", stats.merges as f64 / minutes);
+
+  println!();
+  println!("chain lengths (longest per push):");
+  let pushes: u64 = stats.chains.values().sum();
+  for (chain, count) in &stats.chains {
+    println!(
+      "  {chain:>3}: {count:>9} ({:5.2}%)",
+      100.0 * *count as f64 / pushes as f64
+    );
+  }
+
+  println!();
+  println!("created:        from rows  from merges  from rank ups");
+  for obj_type in coin_set.types() {
+    let rows = stats.from_rows.get(&obj_type).copied().unwrap_or(0);
+    let merges = stats.from_merges.get(&obj_type).copied().unwrap_or(0);
+    let rank_ups = stats.from_rank_ups.get(&obj_type).copied().unwrap_or(0);
+    println!(
+      "  {:<12} {:>9.1}  {:>11.1}  {:>13.1}   per game",
+      coin_set.name(obj_type),
+      rows as f64 / games,
+      merges as f64 / games,
+      rank_ups as f64 / games
+    );
+  }
+}
+
+fn main() {
+  let options = Options::parse().unwrap_or_else(|err| {
+    eprintln!("{err}\n{USAGE}");
+    exit(2);
+  });
+  let coin_set = Arc::new(CoinSet::load(&options.coins).unwrap_or_else(|err| {
+    eprintln!("{err}");
+    exit(1);
+  }));
+
+  let threads = std::thread::available_parallelism().map_or(1, |n| n.get() as u64);
+  let mut stats = Stats::default();
+  std::thread::scope(|scope| {
+    let handles: Vec<_> = (0..threads)
+      .map(|thread| {
+        let coin_set = &coin_set;
+        let options = &options;
+        scope.spawn(move || {
+          let mut stats = Stats::default();
+          for game in (thread..options.games).step_by(threads as usize) {
+            stats.add(play(options.seed + game, coin_set, options));
+          }
+          stats
+        })
+      })
+      .collect();
+    for handle in handles {
+      stats.add(handle.join().expect("a simulation thread panicked"));
+    }
+  });
+  print_report(&stats, &coin_set, &options);
+}
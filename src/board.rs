@@ -0,0 +1,816 @@
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use rand::prelude::*;
+
+use crate::coins::{CoinSet, ItemKind, ObjType};
+
+/// Identifies a single coin on a board for as long as it exists, so the ECS side can keep one
+/// sprite per coin.
+pub type CoinId = u32;
+
+/// The standard board size, as (width, height).
+pub const BOARD_DIM: (i32, i32) = (7, 12);
+/// Widths that leave room for the girl's column.
+pub const WIDTH_RANGE: RangeInclusive<i32> = 3..=20;
+/// Heights that leave room for the starting rows.
+pub const HEIGHT_RANGE: RangeInclusive<i32> = 5..=30;
+
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Position {
+  pub col: i32,
+  pub row: i32,
+}
+impl Position {
+  /// The four orthogonal neighbors, starting with the one above.
+  pub fn neighbors(self) -> [Position; 4] {
+    [(0, -1), (-1, 0), (1, 0), (0, 1)].map(|(d_col, d_row)| Position {
+      col: self.col + d_col,
+      row: self.row + d_row,
+    })
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Coin {
+  pub id: CoinId,
+  pub obj_type: ObjType,
+}
+
+/// Something that happened to a board as the result of a single operation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BoardEvent {
+  Pulled {
+    col: i32,
+    obj_type: ObjType,
+    count: usize,
+  },
+  Pushed {
+    col: i32,
+    obj_type: ObjType,
+    count: usize,
+  },
+  /// `coins` were removed; `upgrade` is the coin that took their place, if the type upgrades.
+  /// `chain` is 0 for a merge caused directly by a push and goes up by one for every chain
+  /// reaction after that.
+  Merged {
+    obj_type: ObjType,
+    coins: Vec<Position>,
+    upgrade: Option<(Coin, Position)>,
+    chain: u32,
+  },
+  /// The `ItemKind::RankUp` item at `position` was used up promoting every coin in `promoted`,
+  /// each listed with the type it became.
+  RankedUp {
+    position: Position,
+    promoted: Vec<(ObjType, Position)>,
+    chain: u32,
+  },
+  /// The `ItemKind::Erase` item at `position` was used up clearing every `obj_type` coin in `coins`.
+  Erased {
+    position: Position,
+    obj_type: ObjType,
+    coins: Vec<Position>,
+    chain: u32,
+  },
+  Fell {
+    id: CoinId,
+    from: Position,
+    to: Position,
+  },
+  RowInserted,
+}
+
+/// One player's board, independent of the ECS.
+///
+/// Row 0 is the top of the board where new rows come in, and coins stack towards it. The girl
+/// stands on row `height - 1`, and the board is lost once any coin reaches row `height`.
+#[derive(Debug, Clone)]
+pub struct BoardState {
+  width: i32,
+  height: i32,
+  /// `columns[col][row]`; columns may grow past `height` when the board is lost.
+  columns: Vec<Vec<Option<Coin>>>,
+  /// Coins the girl is holding, in the order they were pulled.
+  held: Vec<Coin>,
+  /// Positions to check for merges on the next `settle`.
+  pending: Vec<Position>,
+  next_id: CoinId,
+  coin_set: Arc<CoinSet>,
+}
+
+impl BoardState {
+  pub fn new(width: i32, height: i32, coin_set: Arc<CoinSet>) -> Self {
+    BoardState {
+      width,
+      height,
+      columns: vec![Vec::new(); width as usize],
+      held: Vec::new(),
+      pending: Vec::new(),
+      next_id: 0,
+      coin_set,
+    }
+  }
+
+  pub fn width(&self) -> i32 {
+    self.width
+  }
+
+  pub fn height(&self) -> i32 {
+    self.height
+  }
+
+  pub fn coin_set(&self) -> &Arc<CoinSet> {
+    &self.coin_set
+  }
+
+  pub fn get(&self, position: Position) -> Option<Coin> {
+    if position.col < 0 || position.col >= self.width || position.row < 0 {
+      return None;
+    }
+    *self.columns[position.col as usize].get(position.row as usize)?
+  }
+
+  /// Every coin on the board (not including held coins).
+  pub fn coins(&self) -> impl Iterator<Item = (Position, Coin)> + '_ {
+    self.columns.iter().enumerate().flat_map(|(col, column)| {
+      column.iter().enumerate().filter_map(move |(row, coin)| {
+        coin.map(|coin| {
+          (
+            Position {
+              col: col as i32,
+              row: row as i32,
+            },
+            coin,
+          )
+        })
+      })
+    })
+  }
+
+  pub fn held(&self) -> &[Coin] {
+    &self.held
+  }
+
+  pub fn held_type(&self) -> Option<ObjType> {
+    self.held.first().map(|coin| coin.obj_type)
+  }
+
+  /// One past the lowest occupied row of `col`, i.e. the row a pushed coin would land on.
+  pub fn column_height(&self, col: i32) -> i32 {
+    self.columns[col as usize]
+      .iter()
+      .rposition(Option::is_some)
+      .map_or(0, |row| row as i32 + 1)
+  }
+
+  pub fn is_lost(&self) -> bool {
+    (0..self.width).any(|col| self.column_height(col) > self.height)
+  }
+
+  /// Picks up the bottom run of matching coins in `col`. Does nothing if the girl is already
+  /// holding a different type.
+  pub fn pull(&mut self, col: i32) -> Vec<BoardEvent> {
+    let held_type = self.held_type();
+    let column = &mut self.columns[col as usize];
+    let Some(obj_type) = column
+      .iter()
+      .rev()
+      .flatten()
+      .next()
+      .map(|coin| coin.obj_type)
+    else {
+      return Vec::new();
+    };
+    if held_type.is_some_and(|held| held != obj_type) {
+      return Vec::new();
+    }
+    let mut count = 0;
+    for slot in column.iter_mut().rev() {
+      match slot {
+        Some(coin) if coin.obj_type == obj_type => {
+          self.held.push(*coin);
+          *slot = None;
+          count += 1;
+        }
+        Some(_) => break,
+        None => (),
+      }
+    }
+    vec![BoardEvent::Pulled {
+      col,
+      obj_type,
+      count,
+    }]
+  }
+
+  /// Drops everything the girl is holding under `col`. Merges are resolved by `settle`.
+  pub fn push(&mut self, col: i32) -> Vec<BoardEvent> {
+    let Some(obj_type) = self.held_type() else {
+      return Vec::new();
+    };
+    let count = self.held.len();
+    let mut row = self.column_height(col);
+    for coin in std::mem::take(&mut self.held) {
+      self.set(Position { col, row }, Some(coin));
+      row += 1;
+    }
+    self.pending.push(Position { col, row: row - 1 });
+    vec![BoardEvent::Pushed {
+      col,
+      obj_type,
+      count,
+    }]
+  }
+
+  /// Pushes every column down by one and fills the top row with `row`, which needs a coin for
+  /// every column.
+  pub fn insert_row(&mut self, row: &[ObjType]) -> Vec<BoardEvent> {
+    assert_eq!(
+      row.len(),
+      self.width as usize,
+      "a row needs a coin for every column"
+    );
+    for (col, &obj_type) in row.iter().enumerate() {
+      let coin = self.new_coin(obj_type);
+      self.columns[col].insert(0, Some(coin));
+    }
+    for position in &mut self.pending {
+      position.row += 1;
+    }
+    vec![BoardEvent::RowInserted]
+  }
+
+  /// Resolves pending merges and items and closes the gaps they leave behind, repeating for as
+  /// long as an upgraded, promoted or fallen coin sets off another one.
+  pub fn settle(&mut self) -> Vec<BoardEvent> {
+    let mut events = Vec::new();
+    let mut chain = 0;
+    while !self.pending.is_empty() {
+      let mut resolved = false;
+      for position in std::mem::take(&mut self.pending) {
+        let Some(event) = self.resolve_at(position, chain) else {
+          continue;
+        };
+        match &event {
+          BoardEvent::Merged {
+            upgrade: Some((_, upgrade_pos)),
+            ..
+          } => self.pending.push(*upgrade_pos),
+          BoardEvent::RankedUp { promoted, .. } => {
+            self.pending.extend(promoted.iter().map(|&(_, pos)| pos))
+          }
+          _ => (),
+        }
+        events.push(event);
+        resolved = true;
+      }
+      for event in self.fall() {
+        if let BoardEvent::Fell { to, .. } = event {
+          self.pending.push(to);
+        }
+        events.push(event);
+      }
+      if resolved {
+        chain += 1;
+      }
+    }
+    events
+  }
+
+  /// Flood fills from `position` over orthogonally adjacent coins of the same type.
+  pub fn get_connected(&self, position: Position) -> Option<(ObjType, Vec<Position>)> {
+    let obj_type = self.get(position)?.obj_type;
+    let mut set = HashSet::new();
+    let mut stack = vec![position];
+    while let Some(pos) = stack.pop() {
+      if !set.insert(pos) {
+        continue;
+      }
+      for adj in pos.neighbors() {
+        if self.get(adj).map(|coin| coin.obj_type) == Some(obj_type) {
+          stack.push(adj);
+        }
+      }
+    }
+    let mut group: Vec<Position> = set.into_iter().collect();
+    group.sort_by_key(|pos| (pos.col, pos.row));
+    Some((obj_type, group))
+  }
+
+  /// Every coin in the groups orthogonally touching `position`, leaving out items.
+  fn touching_coins(&self, position: Position) -> Vec<Position> {
+    let mut set = HashSet::new();
+    for adj in position.neighbors() {
+      if self
+        .get(adj)
+        .is_none_or(|coin| self.coin_set.is_item(coin.obj_type))
+        || set.contains(&adj)
+      {
+        continue;
+      }
+      if let Some((_, group)) = self.get_connected(adj) {
+        set.extend(group);
+      }
+    }
+    let mut coins: Vec<Position> = set.into_iter().collect();
+    coins.sort_by_key(|pos| (pos.col, pos.row));
+    coins
+  }
+
+  /// Checks the coin at `position` for a merge. If its group is too small to merge but touches
+  /// an item, that item goes off instead.
+  fn resolve_at(&mut self, position: Position, chain: u32) -> Option<BoardEvent> {
+    if self.coin_set.is_item(self.get(position)?.obj_type) {
+      return self.use_item(position, None, chain);
+    }
+    if let Some(event) = self.merge_at(position, chain) {
+      return Some(event);
+    }
+    let (obj_type, group) = self.get_connected(position)?;
+    let item = group.iter().flat_map(|pos| pos.neighbors()).find(|&adj| {
+      self
+        .get(adj)
+        .is_some_and(|coin| self.coin_set.is_item(coin.obj_type))
+    })?;
+    self.use_item(item, Some(obj_type), chain)
+  }
+
+  /// Sets off the item at `position` against the coins touching it. `trigger` is the type of
+  /// the coin that ran into the item, if it was a coin that moved. The item stays on the board if
+  /// it would have no effect.
+  fn use_item(
+    &mut self,
+    position: Position,
+    trigger: Option<ObjType>,
+    chain: u32,
+  ) -> Option<BoardEvent> {
+    match self.coin_set.item(self.get(position)?.obj_type)? {
+      ItemKind::RankUp => {
+        let promoted: Vec<(ObjType, Position)> = self
+          .touching_coins(position)
+          .into_iter()
+          .filter_map(|pos| {
+            let upgrade = self.coin_set.upgrade(self.get(pos)?.obj_type)?;
+            Some((upgrade, pos))
+          })
+          .collect();
+        if promoted.is_empty() {
+          return None;
+        }
+        self.set(position, None);
+        for &(obj_type, pos) in &promoted {
+          let mut coin = self.get(pos)?;
+          coin.obj_type = obj_type;
+          self.set(pos, Some(coin));
+        }
+        Some(BoardEvent::RankedUp {
+          position,
+          promoted,
+          chain,
+        })
+      }
+      ItemKind::Erase => {
+        let obj_type = trigger.or_else(|| {
+          position
+            .neighbors()
+            .into_iter()
+            .filter_map(|adj| self.get(adj))
+            .map(|coin| coin.obj_type)
+            .find(|&obj_type| !self.coin_set.is_item(obj_type))
+        })?;
+        let coins: Vec<Position> = self
+          .coins()
+          .filter(|(_, coin)| coin.obj_type == obj_type)
+          .map(|(pos, _)| pos)
+          .collect();
+        self.set(position, None);
+        for &pos in &coins {
+          self.set(pos, None);
+        }
+        Some(BoardEvent::Erased {
+          position,
+          obj_type,
+          coins,
+          chain,
+        })
+      }
+    }
+  }
+
+  /// Merges the group at `position` if it is big enough. The upgraded coin takes the place of
+  /// the coin at `position`.
+  fn merge_at(&mut self, position: Position, chain: u32) -> Option<BoardEvent> {
+    let (obj_type, coins) = self.get_connected(position)?;
+    if coins.len() < self.coin_set.merge_count(obj_type) {
+      return None;
+    }
+    for &pos in &coins {
+      self.set(pos, None);
+    }
+    let upgrade = self.coin_set.upgrade(obj_type).map(|new_type| {
+      let coin = self.new_coin(new_type);
+      self.set(position, Some(coin));
+      (coin, position)
+    });
+    Some(BoardEvent::Merged {
+      obj_type,
+      coins,
+      upgrade,
+      chain,
+    })
+  }
+
+  /// Moves every coin up into the gaps above it.
+  fn fall(&mut self) -> Vec<BoardEvent> {
+    let mut events = Vec::new();
+    for (col, column) in self.columns.iter_mut().enumerate() {
+      let mut gap_size = 0;
+      for row in 0..column.len() {
+        match column[row] {
+          None => gap_size += 1,
+          Some(coin) if gap_size > 0 => {
+            column[row - gap_size] = Some(coin);
+            column[row] = None;
+            events.push(BoardEvent::Fell {
+              id: coin.id,
+              from: Position {
+                col: col as i32,
+                row: row as i32,
+              },
+              to: Position {
+                col: col as i32,
+                row: (row - gap_size) as i32,
+              },
+            });
+          }
+          Some(_) => (),
+        }
+      }
+      while column.last() == Some(&None) {
+        column.pop();
+      }
+    }
+    events
+  }
+
+  fn set(&mut self, position: Position, coin: Option<Coin>) {
+    let column = &mut self.columns[position.col as usize];
+    let row = position.row as usize;
+    if column.len() <= row {
+      column.resize(row + 1, None);
+    }
+    column[row] = coin;
+  }
+
+  fn new_coin(&mut self, obj_type: ObjType) -> Coin {
+    let id = self.next_id;
+    self.next_id += 1;
+    Coin { id, obj_type }
+  }
+}
+
+/// Rolls a new row using the coin set's spawn weights.
+pub fn random_row<R: Rng + ?Sized>(rng: &mut R, coin_set: &CoinSet, width: i32) -> Vec<ObjType> {
+  (0..width).map(|_| coin_set.random(rng)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Lowercase letters are coins, each merging 3 at a time into the next letter up to `c`. `R`
+  /// and `E` are the RankUp and Erase items.
+  fn coin_set() -> Arc<CoinSet> {
+    let text = r#"(coins: [
+      (name: "a", value: 1, sprite: "a.png", merge_count: 3, upgrade: Some("b"), weight: 1),
+      (name: "b", value: 5, sprite: "b.png", merge_count: 3, upgrade: Some("c"), weight: 1),
+      (name: "c", value: 10, sprite: "c.png", merge_count: 3, weight: 1),
+      (name: "R", sprite: "r.png", weight: 1, item: Some(RankUp)),
+      (name: "E", sprite: "e.png", weight: 1, item: Some(Erase)),
+    ])"#;
+    Arc::new(CoinSet::parse(text).unwrap())
+  }
+
+  fn obj(board: &BoardState, name: char) -> ObjType {
+    board.coin_set().find(&name.to_string()).unwrap()
+  }
+
+  /// A `height` tall board drawn row by row from the top, with `.` for an empty cell.
+  fn board(height: i32, rows: &[&str]) -> BoardState {
+    let width = rows[0].len() as i32;
+    let mut board = BoardState::new(width, height, coin_set());
+    for (row, line) in rows.iter().enumerate() {
+      for (col, name) in line.chars().enumerate() {
+        if name == '.' {
+          continue;
+        }
+        let coin = board.new_coin(obj(&board, name));
+        let position = Position {
+          col: col as i32,
+          row: row as i32,
+        };
+        board.set(position, Some(coin));
+      }
+    }
+    board
+  }
+
+  /// Draws the board the way `board` reads it, down to its lowest coin.
+  fn picture(board: &BoardState) -> Vec<String> {
+    let rows = (0..board.width())
+      .map(|col| board.column_height(col))
+      .max()
+      .unwrap_or(0);
+    (0..rows)
+      .map(|row| {
+        (0..board.width())
+          .map(|col| match board.get(Position { col, row }) {
+            Some(coin) => board.coin_set().name(coin.obj_type).chars().next().unwrap(),
+            None => '.',
+          })
+          .collect()
+      })
+      .collect()
+  }
+
+  fn held(board: &BoardState) -> String {
+    board
+      .held()
+      .iter()
+      .map(|coin| board.coin_set().name(coin.obj_type))
+      .collect()
+  }
+
+  #[test]
+  fn pull_takes_the_bottom_run() {
+    let mut board = board(6, &["a.", "b.", "b."]);
+    let events = board.pull(0);
+    assert_eq!(
+      events,
+      vec![BoardEvent::Pulled {
+        col: 0,
+        obj_type: obj(&board, 'b'),
+        count: 2,
+      }]
+    );
+    assert_eq!(held(&board), "bb");
+    assert_eq!(picture(&board), ["a."]);
+  }
+
+  #[test]
+  fn pull_skips_a_different_type() {
+    let mut board = board(6, &["ab", ".b"]);
+    board.pull(1);
+    assert!(board.pull(0).is_empty());
+    assert_eq!(held(&board), "bb");
+    assert_eq!(picture(&board), ["a."]);
+  }
+
+  #[test]
+  fn pull_from_an_empty_column_does_nothing() {
+    let mut board = board(6, &["a."]);
+    assert!(board.pull(1).is_empty());
+    assert!(board.held().is_empty());
+  }
+
+  #[test]
+  fn push_lands_under_the_lowest_coin() {
+    let mut board = board(6, &["ab", "a.", "c."]);
+    board.pull(1);
+    let events = board.push(0);
+    assert_eq!(
+      events,
+      vec![BoardEvent::Pushed {
+        col: 0,
+        obj_type: obj(&board, 'b'),
+        count: 1,
+      }]
+    );
+    assert!(board.held().is_empty());
+    assert_eq!(picture(&board), ["a.", "a.", "c.", "b."]);
+  }
+
+  #[test]
+  fn push_with_nothing_held_does_nothing() {
+    let mut board = board(6, &["a."]);
+    assert!(board.push(1).is_empty());
+    assert_eq!(picture(&board), ["a."]);
+  }
+
+  #[test]
+  fn insert_row_shifts_every_column_down() {
+    let mut board = board(6, &["a.b", "b.."]);
+    let row = [obj(&board, 'c'), obj(&board, 'c'), obj(&board, 'a')];
+    assert_eq!(board.insert_row(&row), vec![BoardEvent::RowInserted]);
+    assert_eq!(picture(&board), ["cca", "a.b", "b.."]);
+  }
+
+  #[test]
+  #[should_panic(expected = "a coin for every column")]
+  fn insert_row_needs_the_whole_width() {
+    let mut board = board(6, &["a.b"]);
+    let row = [obj(&board, 'c')];
+    board.insert_row(&row);
+  }
+
+  #[test]
+  fn insert_row_keeps_pending_merges_on_their_coin() {
+    let mut board = board(6, &["a", "a"]);
+    let coin = board.new_coin(obj(&board, 'a'));
+    board.held.push(coin);
+    board.push(0);
+    board.insert_row(&[obj(&board, 'c')]);
+    let events = board.settle();
+    assert!(matches!(events[0], BoardEvent::Merged { .. }));
+    assert_eq!(picture(&board), ["c", "b"]);
+  }
+
+  #[test]
+  fn fall_closes_gaps_upwards() {
+    let mut board = board(6, &["a.", "..", "b.", ".c"]);
+    let b = board.get(Position { col: 0, row: 2 }).unwrap();
+    let events = board.fall();
+    assert_eq!(picture(&board), ["ac", "b."]);
+    assert!(events.contains(&BoardEvent::Fell {
+      id: b.id,
+      from: Position { col: 0, row: 2 },
+      to: Position { col: 0, row: 1 },
+    }));
+    assert_eq!(events.len(), 2);
+  }
+
+  #[test]
+  fn lost_once_a_column_passes_the_girl() {
+    let mut board = board(3, &["a", "b", "c"]);
+    assert!(!board.is_lost());
+    board.insert_row(&[obj(&board, 'c')]);
+    assert!(board.is_lost());
+  }
+
+  fn hold(board: &mut BoardState, name: char) {
+    let coin = board.new_coin(obj(board, name));
+    board.held.push(coin);
+  }
+
+  fn merge_chains(events: &[BoardEvent]) -> Vec<(String, u32)> {
+    events
+      .iter()
+      .filter_map(|event| match event {
+        BoardEvent::Merged {
+          obj_type, chain, ..
+        } => Some((obj_type, *chain)),
+        _ => None,
+      })
+      .map(|(&obj_type, chain)| (coin_set().name(obj_type).to_string(), chain))
+      .collect()
+  }
+
+  #[test]
+  fn upgraded_coin_merges_again() {
+    let mut board = board(6, &["bb", "a.", "a."]);
+    hold(&mut board, 'a');
+    board.push(0);
+    let events = board.settle();
+    assert_eq!(
+      merge_chains(&events),
+      [("a".to_string(), 0), ("b".to_string(), 1)]
+    );
+    assert_eq!(picture(&board), ["c."]);
+  }
+
+  #[test]
+  fn merged_coin_can_be_found_after_it_falls() {
+    let mut board = board(6, &["a.", "a."]);
+    hold(&mut board, 'a');
+    board.push(0);
+    let events = board.settle();
+    let BoardEvent::Merged {
+      upgrade: Some((coin, position)),
+      ..
+    } = events[0]
+    else {
+      panic!("expected a merge, got {events:?}");
+    };
+    // it's made where the pushed coin landed, then falls to the top
+    assert_eq!(position, Position { col: 0, row: 2 });
+    assert_eq!(board.get(position), None);
+    let found = board.coins().find(|(_, other)| other.id == coin.id);
+    assert_eq!(found, Some((Position { col: 0, row: 0 }, coin)));
+    assert_eq!(coin.obj_type, obj(&board, 'b'));
+  }
+
+  #[test]
+  fn fallen_coin_sets_off_a_merge() {
+    let mut board = board(6, &["bb", ".c", ".c", ".b"]);
+    hold(&mut board, 'c');
+    board.push(0);
+    let events = board.settle();
+    // the top denomination merges into nothing, leaving a gap the last b falls into
+    assert!(matches!(
+      events[0],
+      BoardEvent::Merged {
+        upgrade: None,
+        chain: 0,
+        ..
+      }
+    ));
+    assert!(events.iter().any(|event| matches!(
+      event,
+      BoardEvent::Fell {
+        from: Position { col: 1, row: 3 },
+        to: Position { col: 1, row: 1 },
+        ..
+      }
+    )));
+    assert_eq!(
+      merge_chains(&events),
+      [("c".to_string(), 0), ("b".to_string(), 1)]
+    );
+    assert_eq!(picture(&board), [".c"]);
+  }
+
+  #[test]
+  fn chain_depth_counts_each_cascade_step() {
+    let mut board = board(6, &["cc", "bb", "a.", "a."]);
+    hold(&mut board, 'a');
+    board.push(0);
+    let events = board.settle();
+    assert_eq!(
+      merge_chains(&events),
+      [
+        ("a".to_string(), 0),
+        ("b".to_string(), 1),
+        ("c".to_string(), 2)
+      ]
+    );
+    assert!(picture(&board).is_empty());
+  }
+
+  #[test]
+  fn settle_without_a_merge_changes_nothing() {
+    let mut board = board(6, &["ab", "a."]);
+    hold(&mut board, 'b');
+    board.push(0);
+    assert!(board.settle().is_empty());
+    assert_eq!(picture(&board), ["ab", "a.", "b."]);
+  }
+
+  #[test]
+  fn rank_up_promotes_into_a_merge() {
+    let mut board = board(6, &["bb.", ".aR"]);
+    hold(&mut board, 'a');
+    board.push(2);
+    let events = board.settle();
+    assert_eq!(
+      events[0],
+      BoardEvent::RankedUp {
+        position: Position { col: 2, row: 1 },
+        promoted: vec![
+          (obj(&board, 'b'), Position { col: 1, row: 1 }),
+          (obj(&board, 'b'), Position { col: 2, row: 2 }),
+        ],
+        chain: 0,
+      }
+    );
+    // the promoted coins go through the merge path like any other
+    assert_eq!(merge_chains(&events), [("b".to_string(), 1)]);
+    assert_eq!(picture(&board), [".c."]);
+  }
+
+  #[test]
+  fn rank_up_with_nothing_to_promote_stays() {
+    let mut board = board(6, &["cR"]);
+    hold(&mut board, 'c');
+    board.push(0);
+    assert!(board.settle().is_empty());
+    assert_eq!(picture(&board), ["cR", "c."]);
+  }
+
+  #[test]
+  fn erase_clears_only_the_touched_denomination() {
+    let mut board = board(6, &["abE", "ba.", "c.."]);
+    hold(&mut board, 'b');
+    board.push(2);
+    let events = board.settle();
+    let BoardEvent::Erased {
+      position,
+      obj_type,
+      coins,
+      chain,
+    } = &events[0]
+    else {
+      panic!("expected an erase, got {:?}", events[0]);
+    };
+    assert_eq!(*position, Position { col: 2, row: 0 });
+    assert_eq!(*obj_type, obj(&board, 'b'));
+    assert_eq!(coins.len(), 3);
+    assert_eq!(*chain, 0);
+    // what's left falls into the gaps
+    assert!(events[1..]
+      .iter()
+      .all(|event| matches!(event, BoardEvent::Fell { .. })));
+    assert_eq!(picture(&board), ["aa.", "c.."]);
+  }
+}
@@ -0,0 +1,96 @@
+use rand::prelude::*;
+
+use crate::board::{BoardEvent, BoardState, Position};
+
+/// A pull from one column followed by a push into another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+  pub pull: i32,
+  pub push: i32,
+}
+
+pub fn random_move(board: &BoardState, rng: &mut impl Rng) -> Option<Move> {
+  let pull = (0..board.width())
+    .filter(|&col| board.column_height(col) > 0)
+    .choose(rng)?;
+  Some(Move {
+    pull,
+    push: rng.gen_range(0..board.width()),
+  })
+}
+
+/// Tries every pull/push pair on a copy of the board, looking `depth` moves ahead. Only the
+/// board is considered; new rows and garbage are left to the next plan.
+pub fn best_move(board: &BoardState, depth: usize) -> Option<(Move, f32)> {
+  let mut best: Option<(Move, f32)> = None;
+  for pull in 0..board.width() {
+    let mut pulled = board.clone();
+    pulled.pull(pull);
+    if pulled.held().is_empty() {
+      continue;
+    }
+    for push in 0..board.width() {
+      let mut next = pulled.clone();
+      let mut events = next.push(push);
+      events.extend(next.settle());
+      let mut value = rate_events(&events);
+      if next.is_lost() {
+        value -= 1000.0;
+      } else if depth > 1 {
+        value += best_move(&next, depth - 1).map_or_else(|| rate_board(&next), |(_, v)| 0.9 * v);
+      } else {
+        value += rate_board(&next);
+      }
+      if best.is_none_or(|(_, best_value)| value > best_value) {
+        best = Some((Move { pull, push }, value));
+      }
+    }
+  }
+  best
+}
+
+fn rate_events(events: &[BoardEvent]) -> f32 {
+  events
+    .iter()
+    .map(|event| match event {
+      BoardEvent::Merged { coins, chain, .. } => 10.0 * coins.len() as f32 + 25.0 * *chain as f32,
+      BoardEvent::Erased { coins, .. } => 4.0 * coins.len() as f32,
+      BoardEvent::RankedUp { promoted, .. } => 4.0 * promoted.len() as f32,
+      _ => 0.0,
+    })
+    .sum()
+}
+
+/// Prefers low stacks and coins sitting next to their own kind.
+fn rate_board(board: &BoardState) -> f32 {
+  let mut value = 0.0;
+  for col in 0..board.width() {
+    let height = board.column_height(col);
+    value -= height as f32;
+    // the last few rows are the danger zone
+    let danger = height - (board.height() - 3);
+    if danger > 0 {
+      value -= 30.0 * danger as f32;
+    }
+  }
+  for (pos, coin) in board.coins() {
+    for adj in [
+      Position {
+        col: pos.col + 1,
+        ..pos
+      },
+      Position {
+        row: pos.row + 1,
+        ..pos
+      },
+    ] {
+      if board
+        .get(adj)
+        .is_some_and(|other| other.obj_type == coin.obj_type)
+      {
+        value += 2.0;
+      }
+    }
+  }
+  value
+}
@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::coins::CoinSet;
+
+/// The `CoinSet` for every board in the game.
+#[derive(Resource, Clone, Deref)]
+pub struct Coins(pub Arc<CoinSet>);
+
+pub struct CoinsPlugin;
+impl Plugin for CoinsPlugin {
+  fn build(&self, app: &mut App) {
+    let path = std::env::var_os("COINGIRL_COINS")
+      .map(PathBuf::from)
+      .unwrap_or_else(CoinSet::default_path);
+    // there's nothing to play with without coins
+    let coin_set = CoinSet::load(&path).unwrap_or_else(|err| panic!("{err}"));
+    app.insert_resource(Coins(Arc::new(coin_set)));
+  }
+}
@@ -0,0 +1,304 @@
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A kind of coin or item, as an index into the `CoinSet` it came from.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ObjType(u8);
+
+/// What an item does when it goes off.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+pub enum ItemKind {
+  /// Promotes every coin in the groups touching it.
+  RankUp,
+  /// Clears every coin on the board of the type that ran into it.
+  Erase,
+}
+
+/// One entry of a coin set file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CoinDef {
+  /// The denomination, e.g. `"Fifty"`. Other entries refer to this one by it.
+  pub name: String,
+  /// What a single coin is worth towards the score.
+  #[serde(default)]
+  pub value: u32,
+  /// Image under `assets/`.
+  pub sprite: String,
+  /// How many connected coins it takes to merge.
+  #[serde(default = "default_merge_count")]
+  pub merge_count: usize,
+  /// The `name` of the coin a merge turns into. The top denomination has none.
+  #[serde(default)]
+  pub upgrade: Option<String>,
+  /// How often it shows up in new rows, relative to the other entries.
+  pub weight: u32,
+  /// Set for items, which go off against the coins they touch instead of merging.
+  #[serde(default)]
+  pub item: Option<ItemKind>,
+}
+
+fn default_merge_count() -> usize {
+  2
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CoinSetFile {
+  coins: Vec<CoinDef>,
+}
+
+/// Every coin and item a game can use, loaded from `assets/coins.ron`.
+#[derive(Debug)]
+pub struct CoinSet {
+  defs: Vec<CoinDef>,
+  upgrades: Vec<Option<ObjType>>,
+  weights: WeightedIndex<u32>,
+}
+
+impl CoinSet {
+  /// Where the coin set is read from, unless `COINGIRL_COINS` points somewhere else. Like the
+  /// game's other assets, that's under the crate root when run through cargo and next to the
+  /// executable otherwise.
+  pub fn default_path() -> PathBuf {
+    let base = std::env::var_os("CARGO_MANIFEST_DIR")
+      .map(PathBuf::from)
+      .or_else(|| {
+        let exe = std::env::current_exe().ok()?;
+        exe.parent().map(Path::to_path_buf)
+      })
+      .unwrap_or_default();
+    base.join("assets").join("coins.ron")
+  }
+
+  pub fn load(path: &Path) -> Result<Self, String> {
+    let text =
+      fs::read_to_string(path).map_err(|err| format!("can't read {}: {err}", path.display()))?;
+    CoinSet::parse(&text).map_err(|err| format!("invalid coin set {}: {err}", path.display()))
+  }
+
+  pub fn parse(text: &str) -> Result<Self, String> {
+    let file: CoinSetFile = ron::from_str(text).map_err(|err| err.to_string())?;
+    CoinSet::new(file.coins)
+  }
+
+  /// Checks that every entry makes sense and links up the upgrades.
+  pub fn new(defs: Vec<CoinDef>) -> Result<Self, String> {
+    if defs.is_empty() {
+      return Err("there are no coins".to_string());
+    }
+    if defs.len() > u8::MAX as usize {
+      return Err(format!("{} entries is too many", defs.len()));
+    }
+    let mut by_name = HashMap::new();
+    for (i, def) in defs.iter().enumerate() {
+      if by_name.insert(def.name.clone(), ObjType(i as u8)).is_some() {
+        return Err(format!("{:?} is defined more than once", def.name));
+      }
+    }
+    let mut upgrades = Vec::new();
+    for def in &defs {
+      let entry_err = |msg: String| format!("{:?}: {msg}", def.name);
+      if def.sprite.is_empty() {
+        return Err(entry_err("sprite is empty".to_string()));
+      }
+      if def.item.is_some() {
+        if def.upgrade.is_some() {
+          return Err(entry_err("items can't upgrade".to_string()));
+        }
+      } else if def.merge_count < 2 {
+        return Err(entry_err(format!(
+          "merge_count is {}, it needs to be at least 2",
+          def.merge_count
+        )));
+      }
+      let upgrade = match &def.upgrade {
+        Some(name) if name == &def.name => {
+          return Err(entry_err("upgrades to itself".to_string()));
+        }
+        Some(name) => match by_name.get(name.as_str()) {
+          Some(&obj_type) if defs[obj_type.0 as usize].item.is_some() => {
+            return Err(entry_err(format!("upgrades to {name:?}, which is an item")));
+          }
+          Some(&obj_type) => Some(obj_type),
+          None => {
+            return Err(entry_err(format!(
+              "upgrades to {name:?}, which isn't defined"
+            )))
+          }
+        },
+        None => None,
+      };
+      upgrades.push(upgrade);
+    }
+    let weights = WeightedIndex::new(defs.iter().map(|def| def.weight)).map_err(|_| {
+      let names: Vec<&str> = defs.iter().map(|def| def.name.as_str()).collect();
+      format!("{names:?}: every weight is 0, so no coins would ever spawn")
+    })?;
+    Ok(CoinSet {
+      defs,
+      upgrades,
+      weights,
+    })
+  }
+
+  /// Every type in the set, in file order.
+  pub fn types(&self) -> impl Iterator<Item = ObjType> {
+    (0..self.defs.len()).map(|i| ObjType(i as u8))
+  }
+
+  pub fn get(&self, obj_type: ObjType) -> &CoinDef {
+    &self.defs[obj_type.0 as usize]
+  }
+
+  pub fn find(&self, name: &str) -> Option<ObjType> {
+    self
+      .defs
+      .iter()
+      .position(|def| def.name == name)
+      .map(|i| ObjType(i as u8))
+  }
+
+  pub fn name(&self, obj_type: ObjType) -> &str {
+    &self.get(obj_type).name
+  }
+
+  pub fn sprite(&self, obj_type: ObjType) -> &str {
+    &self.get(obj_type).sprite
+  }
+
+  pub fn value(&self, obj_type: ObjType) -> u32 {
+    self.get(obj_type).value
+  }
+
+  pub fn merge_count(&self, obj_type: ObjType) -> usize {
+    self.get(obj_type).merge_count
+  }
+
+  pub fn upgrade(&self, obj_type: ObjType) -> Option<ObjType> {
+    self.upgrades[obj_type.0 as usize]
+  }
+
+  pub fn item(&self, obj_type: ObjType) -> Option<ItemKind> {
+    self.get(obj_type).item
+  }
+
+  pub fn is_item(&self, obj_type: ObjType) -> bool {
+    self.item(obj_type).is_some()
+  }
+
+  /// Picks a coin or item using the spawn weights.
+  pub fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> ObjType {
+    ObjType(self.weights.sample(rng) as u8)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A coin that merges in pairs and doesn't upgrade.
+  fn coin(name: &str) -> CoinDef {
+    CoinDef {
+      name: name.to_string(),
+      value: 1,
+      sprite: format!("{name}.png"),
+      merge_count: 2,
+      upgrade: None,
+      weight: 1,
+      item: None,
+    }
+  }
+
+  fn upgrading(name: &str, upgrade: &str) -> CoinDef {
+    CoinDef {
+      upgrade: Some(upgrade.to_string()),
+      ..coin(name)
+    }
+  }
+
+  fn item(name: &str) -> CoinDef {
+    CoinDef {
+      item: Some(ItemKind::RankUp),
+      ..coin(name)
+    }
+  }
+
+  fn err(defs: Vec<CoinDef>) -> String {
+    CoinSet::new(defs).unwrap_err()
+  }
+
+  #[test]
+  fn shipped_coin_set_loads() {
+    let coin_set = CoinSet::load(&CoinSet::default_path()).unwrap();
+    let one = coin_set.find("One").unwrap();
+    assert_eq!(coin_set.upgrade(one), coin_set.find("Five"));
+    assert!(coin_set.types().any(|obj_type| coin_set.is_item(obj_type)));
+  }
+
+  #[test]
+  fn upgrades_link_by_name() {
+    let coin_set = CoinSet::new(vec![upgrading("a", "b"), coin("b")]).unwrap();
+    let (a, b) = (coin_set.find("a").unwrap(), coin_set.find("b").unwrap());
+    assert_eq!(coin_set.upgrade(a), Some(b));
+    assert_eq!(coin_set.upgrade(b), None);
+  }
+
+  #[test]
+  fn duplicate_name_is_refused() {
+    let msg = err(vec![coin("a"), coin("b"), coin("a")]);
+    assert!(msg.contains(r#""a" is defined more than once"#), "{msg}");
+  }
+
+  #[test]
+  fn unknown_upgrade_is_refused() {
+    let msg = err(vec![coin("a"), upgrading("b", "z")]);
+    assert!(msg.starts_with(r#""b": upgrades to "z", which isn't defined"#), "{msg}");
+  }
+
+  #[test]
+  fn self_upgrade_is_refused() {
+    let msg = err(vec![coin("a"), upgrading("b", "b")]);
+    assert!(msg.starts_with(r#""b": upgrades to itself"#), "{msg}");
+  }
+
+  #[test]
+  fn upgrade_into_an_item_is_refused() {
+    let msg = err(vec![upgrading("a", "R"), item("R")]);
+    assert!(
+      msg.starts_with(r#""a": upgrades to "R", which is an item"#),
+      "{msg}"
+    );
+  }
+
+  #[test]
+  fn merge_count_below_two_is_refused() {
+    let msg = err(vec![
+      coin("a"),
+      CoinDef {
+        merge_count: 1,
+        ..coin("b")
+      },
+    ]);
+    assert!(msg.starts_with(r#""b": merge_count is 1"#), "{msg}");
+  }
+
+  #[test]
+  fn all_zero_weights_are_refused() {
+    let msg = err(vec![
+      CoinDef {
+        weight: 0,
+        ..coin("a")
+      },
+      CoinDef {
+        weight: 0,
+        ..coin("b")
+      },
+    ]);
+    assert!(msg.starts_with(r#"["a", "b"]: every weight is 0"#), "{msg}");
+  }
+}
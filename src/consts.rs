@@ -10,56 +10,16 @@ pub enum AppState {
   Menu,
   Game,
   Lost,
+  EnterInitials,
+  HighScores,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Component)]
-pub enum ObjType {
-  One,
-  Five,
-  Ten,
-  Fifty,
-  OneHundred,
-  FiveHundred,
-  RankUp,
-  EraseItem,
-}
-impl ObjType {
-  pub fn get_path(&self) -> &str {
-    match self {
-      ObjType::One => "one.png",
-      ObjType::Five => "five.png",
-      ObjType::Ten => "ten.png",
-      ObjType::Fifty => "fifty.png",
-      ObjType::OneHundred => "one_hundred.png",
-      ObjType::FiveHundred => "five_hundred.png",
-      ObjType::RankUp => "rank_up.png",
-      ObjType::EraseItem => "erase.png",
-    }
-  }
-  pub fn get_merge_count(&self) -> usize {
-    match self {
-      ObjType::One => 5,
-      ObjType::Five => 2,
-      ObjType::Ten => 5,
-      ObjType::Fifty => 2,
-      ObjType::OneHundred => 5,
-      ObjType::FiveHundred => 2,
-      ObjType::RankUp => 2,
-      ObjType::EraseItem => 2,
-    }
-  }
-  pub fn get_upgrade(&self) -> Option<Self> {
-    match self {
-      ObjType::One => Some(ObjType::Five),
-      ObjType::Five => Some(ObjType::Ten),
-      ObjType::Ten => Some(ObjType::Fifty),
-      ObjType::Fifty => Some(ObjType::OneHundred),
-      ObjType::OneHundred => Some(ObjType::FiveHundred),
-      ObjType::FiveHundred => None,
-      ObjType::RankUp => None,
-      ObjType::EraseItem => None,
-    }
-  }
+/// Whether a game in progress is paused. Only meaningful in `AppState::Game`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum PauseState {
+  #[default]
+  Running,
+  Paused,
 }
 
 // Resolution
@@ -73,6 +33,14 @@ pub const COIN_SIZE: Vec3 = Vec3::splat(COIN_SIZE_PX);
 pub const GIRL_SIZE_FACTOR: f32 = 1.25;
 pub const GIRL_SIZE: Vec3 = Vec3::splat(COIN_SIZE_PX * GIRL_SIZE_FACTOR);
 pub const GAP_BETWEEN_COINS: f32 = 3.0;
+/// How big the next row's preview coins are next to the board's.
+pub const PREVIEW_SCALE: f32 = 0.6;
+/// Coins' worth of room kept free at either side of a board for the score and other readouts.
+pub const HUD_COLS: f32 = 2.5;
+/// The readouts' font size, in coins.
+pub const HUD_FONT_SCALE: f32 = 0.4;
+
+pub const BOARD_COLOR: Color = Color::rgb(0.5, 0.5, 0.5);
 
 // Z indices
 pub const BACKGROUND_Z: f32 = 0.0;
@@ -81,58 +49,11 @@ pub const GIRL_Z: f32 = 2.0;
 pub const COIN_Z: f32 = 10.0;
 pub const UI_Z: f32 = 20.0;
 
-// Board Dimensions (width, height)
-pub const BOARD_DIM: (i32, i32) = (7, 12);
-
-#[derive(Component, Clone, Copy)]
-pub struct ObjInfo {
-  pub obj_type: ObjType,
-  pub weight: i32,
-  pub color: Color,
-}
+// New row speed curves
+pub use crate::curve::{SOLO_SPEED, VERSUS_SPEED};
 
-pub const BOARD_OBJS: [ObjInfo; 8] = [
-  ObjInfo {
-    obj_type: ObjType::One,
-    weight: 10,
-    color: Color::rgb(0.0, 0.0, 0.0),
-  },
-  ObjInfo {
-    obj_type: ObjType::Five,
-    weight: 10,
-    color: Color::rgb(10.0, 10.0, 10.0),
-  },
-  ObjInfo {
-    obj_type: ObjType::Ten,
-    weight: 10,
-    color: Color::rgb(20.0, 20.0, 20.0),
-  },
-  ObjInfo {
-    obj_type: ObjType::Fifty,
-    weight: 10,
-    color: Color::rgb(20.0, 0.0, 0.0),
-  },
-  ObjInfo {
-    obj_type: ObjType::OneHundred,
-    weight: 10,
-    color: Color::rgb(0.0, 20.0, 0.0),
-  },
-  ObjInfo {
-    obj_type: ObjType::FiveHundred,
-    weight: 5,
-    color: Color::rgb(0.0, 0.0, 20.0),
-  },
-  ObjInfo {
-    obj_type: ObjType::RankUp,
-    weight: 1,
-    color: Color::rgb(100.0, 100.0, 0.0),
-  },
-  ObjInfo {
-    obj_type: ObjType::EraseItem,
-    weight: 1,
-    color: Color::rgb(0.0, 100.0, 100.0),
-  },
-];
+// Default board dimensions (width, height), see `Rules`
+pub use crate::board::BOARD_DIM;
 
 pub const GAME_BOARD: UiRect = UiRect {
   left: Val::Percent(20.0),
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+use rand::prelude::*;
+
+use crate::bot::{best_move, random_move, Move};
+use crate::consts::AppState;
+use crate::game::{ActionType, Board, CoinGirl, GameActionEvent, GridPos, SendActions};
+use crate::input::Player;
+use crate::rng::GameRng;
+use crate::GameInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Difficulty {
+  Easy,
+  #[default]
+  Normal,
+  Hard,
+}
+
+impl Difficulty {
+  pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Difficulty::Easy => "Easy",
+      Difficulty::Normal => "Normal",
+      Difficulty::Hard => "Hard",
+    }
+  }
+
+  /// Time between the CPU's actions, including the first one of a new plan.
+  fn reaction_secs(self) -> f32 {
+    match self {
+      Difficulty::Easy => 0.7,
+      Difficulty::Normal => 0.4,
+      Difficulty::Hard => 0.2,
+    }
+  }
+
+  /// How many pull/push moves ahead the CPU looks. Three takes longer than a frame, which is why
+  /// the search runs on `AsyncComputeTaskPool`.
+  fn search_depth(self) -> usize {
+    match self {
+      Difficulty::Easy => 1,
+      Difficulty::Normal => 2,
+      Difficulty::Hard => 3,
+    }
+  }
+
+  /// Chance of playing a random move instead of the best one.
+  fn blunder_chance(self) -> f64 {
+    match self {
+      Difficulty::Easy => 0.3,
+      Difficulty::Normal => 0.1,
+      Difficulty::Hard => 0.0,
+    }
+  }
+}
+
+/// Marks a `CoinGirl` as played by the computer. Human input skips her.
+#[derive(Component)]
+pub struct Cpu {
+  difficulty: Difficulty,
+  timer: Timer,
+  plan: VecDeque<ActionType>,
+  /// The search for the next move, while it's running.
+  thinking: Option<Task<Option<Move>>>,
+}
+
+impl Cpu {
+  fn new(difficulty: Difficulty) -> Self {
+    Cpu {
+      difficulty,
+      timer: Timer::from_seconds(difficulty.reaction_secs(), TimerMode::Repeating),
+      plan: VecDeque::new(),
+      thinking: None,
+    }
+  }
+}
+
+pub struct CpuPlugin;
+impl Plugin for CpuPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems((
+      add_cpu.in_set(OnUpdate(AppState::Game)),
+      cpu_input
+        .after(add_cpu)
+        .in_set(SendActions)
+        .in_set(OnUpdate(AppState::Game)),
+    ));
+  }
+}
+
+/// Hands P2 over to the CPU in a Vs CPU game.
+fn add_cpu(
+  mut commands: Commands,
+  game_info: Res<GameInfo>,
+  girl_query: Query<(Entity, &Player), Added<CoinGirl>>,
+) {
+  if !game_info.cpu {
+    return;
+  }
+  for (entity, &player) in &girl_query {
+    if player == Player::P2 {
+      commands
+        .entity(entity)
+        .insert(Cpu::new(game_info.difficulty));
+    }
+  }
+}
+
+fn cpu_input(
+  time: Res<Time>,
+  mut game_rng: ResMut<GameRng>,
+  mut girl_query: Query<(&Player, &GridPos, &Board, &mut Cpu), With<CoinGirl>>,
+  mut action_writer: EventWriter<GameActionEvent>,
+) {
+  for (&player, girl_pos, board, mut cpu) in &mut girl_query {
+    if !cpu.timer.tick(time.delta()).just_finished() {
+      continue;
+    }
+    if let Some(task) = &mut cpu.thinking {
+      // she keeps still until the search is done
+      let Some(best) = future::block_on(future::poll_once(task)) else {
+        continue;
+      };
+      cpu.thinking = None;
+      if let Some(best) = best {
+        cpu.plan = plan_actions(girl_pos.col, best);
+      }
+      continue;
+    }
+    if cpu.plan.is_empty() {
+      // thinking takes up at least this turn
+      let rng = game_rng.cpu(player);
+      if rng.gen_bool(cpu.difficulty.blunder_chance()) {
+        if let Some(best) = random_move(board, rng) {
+          cpu.plan = plan_actions(girl_pos.col, best);
+        }
+      } else {
+        let board = (**board).clone();
+        let depth = cpu.difficulty.search_depth();
+        cpu.thinking = Some(
+          AsyncComputeTaskPool::get()
+            .spawn(async move { best_move(&board, depth).map(|(best, _)| best) }),
+        );
+      }
+      continue;
+    }
+    if let Some(action_type) = cpu.plan.pop_front() {
+      action_writer.send(GameActionEvent {
+        player,
+        action_type,
+      });
+    }
+  }
+}
+
+/// The moves, pull and push that carry out `best` for a girl standing in `col`.
+fn plan_actions(col: i32, best: Move) -> VecDeque<ActionType> {
+  let mut plan = VecDeque::new();
+  walk(&mut plan, col, best.pull);
+  plan.push_back(ActionType::CoinPull);
+  walk(&mut plan, best.pull, best.push);
+  plan.push_back(ActionType::CoinPush);
+  plan
+}
+
+fn walk(plan: &mut VecDeque<ActionType>, from: i32, to: i32) {
+  let step = if to < from {
+    ActionType::MoveLeft
+  } else {
+    ActionType::MoveRight
+  };
+  plan.extend(std::iter::repeat_n(step, from.abs_diff(to) as usize));
+}
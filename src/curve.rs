@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+/// How the new-row interval shrinks over a game. The level goes up once every `level_secs`, and
+/// once for every `level_points` of the player's score if set.
+#[derive(Debug, Clone, Copy)]
+pub struct SpeedCurve {
+  pub start_secs: f32,
+  pub min_secs: f32,
+  /// Seconds taken off the interval per level.
+  pub step_secs: f32,
+  pub level_secs: f32,
+  pub level_points: Option<u32>,
+}
+
+impl SpeedCurve {
+  pub fn for_players(players: usize) -> Self {
+    if players == 2 {
+      VERSUS_SPEED
+    } else {
+      SOLO_SPEED
+    }
+  }
+
+  pub fn level(&self, elapsed: Duration, score: u32) -> u32 {
+    let time_levels = (elapsed.as_secs_f32() / self.level_secs) as u32;
+    let score_levels = self.level_points.map_or(0, |points| score / points);
+    time_levels + score_levels
+  }
+
+  pub fn interval(&self, level: u32) -> Duration {
+    Duration::from_secs_f32((self.start_secs - self.step_secs * level as f32).max(self.min_secs))
+  }
+}
+
+pub const SOLO_SPEED: SpeedCurve = SpeedCurve {
+  start_secs: 4.5,
+  min_secs: 1.5,
+  step_secs: 0.25,
+  level_secs: 60.0,
+  level_points: Some(1000),
+};
+pub const VERSUS_SPEED: SpeedCurve = SpeedCurve {
+  start_secs: 4.5,
+  min_secs: 2.0,
+  step_secs: 0.25,
+  level_secs: 45.0,
+  level_points: None,
+};
@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::board::BoardState;
+use crate::consts::*;
+use crate::game::{Board, BoardBackground, CoinGirl};
+use crate::input::Player;
+use crate::rules::Rules;
+use crate::RenderInfo;
+
+/// How close a column's lowest coin can get to the girl before her board is in danger.
+pub const DANGER_ROWS: i32 = 3;
+
+const DANGER_COLOR: Color = Color::rgb(0.6, 0.12, 0.12);
+
+/// Empty rows left at the bottom of `col`, counting the girl's. Once it's 0, one more coin in the
+/// column loses the game.
+pub fn rows_left(board: &BoardState, col: i32) -> i32 {
+  board.height() - board.column_height(col)
+}
+
+/// The columns of a girl's board that are within `DANGER_ROWS` of losing.
+#[derive(Component, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Danger {
+  pub columns: Vec<i32>,
+  /// The fewest rows left in any column, if any are in danger.
+  pub rows_left: Option<i32>,
+}
+
+impl Danger {
+  pub fn of(board: &BoardState) -> Self {
+    let mut danger = Danger::default();
+    for col in 0..board.width() {
+      let rows = rows_left(board, col);
+      if rows <= DANGER_ROWS {
+        danger.columns.push(col);
+        danger.rows_left = Some(danger.rows_left.map_or(rows, |least| least.min(rows)));
+      }
+    }
+    danger
+  }
+
+  pub fn is_active(&self) -> bool {
+    !self.columns.is_empty()
+  }
+}
+
+/// Sent whenever the columns in danger on `player`'s board change. `danger` is inactive once the
+/// board is safe again.
+pub struct DangerEvent {
+  pub player: Player,
+  pub danger: Danger,
+  /// The board was safe until now.
+  pub started: bool,
+}
+
+/// Flashes over a column in danger.
+#[derive(Component)]
+struct DangerColumn {
+  col: i32,
+}
+
+pub struct DangerPlugin;
+impl Plugin for DangerPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_event::<DangerEvent>().add_systems((
+      setup_danger_columns.in_schedule(OnEnter(AppState::Game)),
+      update_danger.in_set(OnUpdate(AppState::Game)),
+      show_danger
+        .after(update_danger)
+        .in_set(OnUpdate(AppState::Game)),
+      cleanup_danger_columns.in_schedule(OnExit(AppState::Game)),
+    ));
+  }
+}
+
+fn setup_danger_columns(mut commands: Commands, rules: Res<Rules>) {
+  for player in Player::ALL {
+    for col in 0..rules.board_width {
+      commands.spawn((
+        SpriteBundle {
+          sprite: Sprite {
+            color: DANGER_COLOR,
+            anchor: Anchor::TopCenter,
+            ..default()
+          },
+          visibility: Visibility::Hidden,
+          ..default()
+        },
+        DangerColumn { col },
+        player,
+      ));
+    }
+  }
+}
+
+fn update_danger(
+  mut girl_query: Query<(&Player, &Board, &mut Danger), (With<CoinGirl>, Changed<Board>)>,
+  mut danger_writer: EventWriter<DangerEvent>,
+) {
+  for (&player, board, mut danger) in &mut girl_query {
+    let new_danger = Danger::of(board);
+    if *danger == new_danger {
+      continue;
+    }
+    danger_writer.send(DangerEvent {
+      player,
+      danger: new_danger.clone(),
+      started: !danger.is_active() && new_danger.is_active(),
+    });
+    *danger = new_danger;
+  }
+}
+
+/// Pulses the columns in danger and tints the board behind them, more the closer it gets.
+fn show_danger(
+  time: Res<Time>,
+  render_info: Res<RenderInfo>,
+  girl_query: Query<(&Player, &Danger), With<CoinGirl>>,
+  mut column_query: Query<(
+    &DangerColumn,
+    &Player,
+    &mut Sprite,
+    &mut Transform,
+    &mut Visibility,
+  )>,
+  background_query: Query<(&Player, &Handle<ColorMaterial>), With<BoardBackground>>,
+  mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+  let pulse = 0.5 + 0.5 * (time.elapsed_seconds() * 8.0).sin();
+  for (&player, danger) in &girl_query {
+    for (column, &column_player, mut sprite, mut transform, mut visibility) in &mut column_query {
+      if column_player != player {
+        continue;
+      }
+      if !danger.columns.contains(&column.col) {
+        *visibility = Visibility::Hidden;
+        continue;
+      }
+      *visibility = Visibility::Visible;
+      sprite.color.set_a(0.15 + 0.25 * pulse);
+      sprite.custom_size = Some(Vec2::new(
+        render_info.coin_size,
+        render_info.board_height as f32 * render_info.coin_size,
+      ));
+      transform.translation = (render_info.obj_translate(player, column.col, 0)
+        + Vec2::new(0.0, 0.5) * render_info.coin_size)
+        .extend(GIRL_Z - 0.6);
+    }
+
+    // a third of the way at the edge of danger, all the way with a coin right above the girl
+    let color = match danger.rows_left {
+      Some(rows) => {
+        let severity = ((DANGER_ROWS + 1 - rows) as f32 / DANGER_ROWS as f32).min(1.0);
+        lerp_color(BOARD_COLOR, DANGER_COLOR, severity * (0.5 + 0.5 * pulse))
+      }
+      None => BOARD_COLOR,
+    };
+    for (&background_player, material) in &background_query {
+      if background_player != player {
+        continue;
+      }
+      // only touch the material when it changes, so it isn't sent to the GPU every frame
+      if materials.get(material).map(|material| material.color) == Some(color) {
+        continue;
+      }
+      if let Some(material) = materials.get_mut(material) {
+        material.color = color;
+      }
+    }
+  }
+}
+
+fn lerp_color(from: Color, to: Color, amount: f32) -> Color {
+  let from = Vec4::from(from.as_rgba_f32());
+  let to = Vec4::from(to.as_rgba_f32());
+  let mixed = from.lerp(to, amount);
+  Color::rgba(mixed.x, mixed.y, mixed.z, mixed.w)
+}
+
+fn cleanup_danger_columns(mut commands: Commands, query: Query<Entity, With<DangerColumn>>) {
+  for entity in &query {
+    commands.entity(entity).despawn_recursive();
+  }
+}
@@ -0,0 +1,271 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::board::CoinId;
+use crate::consts::*;
+use crate::game::{EraseEvent, MergeEvent, ObjId};
+use crate::input::Player;
+use crate::settings::Settings;
+use crate::RenderInfo;
+
+const MERGE_COLOR: Color = Color::rgb(1.0, 0.85, 0.3);
+const CHAIN_COLOR: Color = Color::rgb(1.0, 0.6, 0.9);
+const ERASE_COLOR: Color = Color::rgb(0.5, 0.8, 1.0);
+
+/// The most particles a single merge or erase can make.
+const MAX_PARTICLES: usize = 80;
+
+/// A bit of a burst or sparkle. Gone once `timer` runs out.
+#[derive(Component)]
+struct Particle {
+  velocity: Vec2,
+  timer: Timer,
+  size: f32,
+  /// Flickers in size instead of staying solid.
+  sparkle: bool,
+}
+
+/// Swells the coin a merge made, then settles it back to its normal size.
+#[derive(Component)]
+struct Pop {
+  timer: Timer,
+  /// How much bigger the coin gets at the peak, as a fraction of its size.
+  strength: f32,
+}
+
+/// Merges whose new coin hasn't been found yet. Its sprite only exists once the board's sprites
+/// have been synced.
+#[derive(Resource, Default)]
+struct PendingPops(Vec<(Player, CoinId, f32)>);
+
+pub struct EffectsPlugin;
+impl Plugin for EffectsPlugin {
+  fn build(&self, app: &mut App) {
+    app.init_resource::<PendingPops>().add_systems((
+      spawn_effects.in_set(OnUpdate(AppState::Game)),
+      update_particles.in_set(OnUpdate(AppState::Game)),
+      update_pops.in_set(OnUpdate(AppState::Game)),
+      start_pops.in_base_set(CoreSet::PostUpdate),
+      cleanup_effects.in_schedule(OnExit(AppState::Game)),
+    ));
+  }
+}
+
+/// How big an effect is. Grows with the number of coins and how deep into a chain it happened.
+fn intensity(count: usize, chain: u32) -> f32 {
+  count as f32 * (1.0 + 0.5 * chain as f32)
+}
+
+/// Turns merges and erases into bursts, with sparkles for chains. Effects only ever use
+/// `thread_rng`, so they can't change what a replay deals.
+fn spawn_effects(
+  mut commands: Commands,
+  mut merge_events: EventReader<MergeEvent>,
+  mut erase_events: EventReader<EraseEvent>,
+  mut pending_pops: ResMut<PendingPops>,
+  settings: Res<Settings>,
+  render_info: Res<RenderInfo>,
+) {
+  if !settings.effects {
+    merge_events.clear();
+    erase_events.clear();
+    return;
+  }
+  let mut rng = thread_rng();
+  for ev in merge_events.iter() {
+    let center = render_info.obj_translate(ev.player, ev.position.col, ev.position.row);
+    let intensity = intensity(ev.count, ev.chain);
+    burst(
+      &mut commands,
+      &mut rng,
+      center,
+      MERGE_COLOR,
+      intensity,
+      render_info.coin_size,
+    );
+    if ev.chain > 0 {
+      sparkle(
+        &mut commands,
+        &mut rng,
+        center,
+        ev.chain,
+        render_info.coin_size,
+      );
+    }
+    // the top denomination merges into nothing, so there's no coin to pop
+    if let Some(id) = ev.new_coin {
+      pending_pops
+        .0
+        .push((ev.player, id, (0.1 * intensity).clamp(0.2, 0.8)));
+    }
+  }
+  for ev in erase_events.iter() {
+    let center = render_info.obj_translate(ev.player, ev.position.col, ev.position.row);
+    // an erase clears coins from all over the board, so it gets a bigger bang
+    burst(
+      &mut commands,
+      &mut rng,
+      center,
+      ERASE_COLOR,
+      2.0 * intensity(ev.count, ev.chain),
+      render_info.coin_size,
+    );
+    if ev.chain > 0 {
+      sparkle(
+        &mut commands,
+        &mut rng,
+        center,
+        ev.chain,
+        render_info.coin_size,
+      );
+    }
+  }
+}
+
+/// Flings particles out in every direction from `center`.
+fn burst(
+  commands: &mut Commands,
+  rng: &mut ThreadRng,
+  center: Vec2,
+  color: Color,
+  intensity: f32,
+  coin_size: f32,
+) {
+  let count = ((4.0 * intensity) as usize).clamp(6, MAX_PARTICLES);
+  let speed = coin_size * (2.0 + 0.25 * intensity).min(8.0);
+  for _ in 0..count {
+    let angle = rng.gen_range(0.0..TAU);
+    let velocity = Vec2::from_angle(angle) * speed * rng.gen_range(0.5..1.0);
+    let size = coin_size * rng.gen_range(0.08..0.18);
+    spawn_particle(
+      commands,
+      center,
+      velocity,
+      color,
+      size,
+      rng.gen_range(0.3..0.6),
+      false,
+    );
+  }
+}
+
+/// Scatters twinkling specks around `center`, more of them for deeper chains.
+fn sparkle(commands: &mut Commands, rng: &mut ThreadRng, center: Vec2, chain: u32, coin_size: f32) {
+  let count = (4 * chain as usize).min(MAX_PARTICLES / 2);
+  for _ in 0..count {
+    let offset = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * coin_size;
+    let velocity = Vec2::new(0.0, coin_size * rng.gen_range(0.3..1.0));
+    let size = coin_size * rng.gen_range(0.1..0.2);
+    spawn_particle(
+      commands,
+      center + offset,
+      velocity,
+      CHAIN_COLOR,
+      size,
+      rng.gen_range(0.5..0.9),
+      true,
+    );
+  }
+}
+
+fn spawn_particle(
+  commands: &mut Commands,
+  position: Vec2,
+  velocity: Vec2,
+  color: Color,
+  size: f32,
+  lifetime: f32,
+  sparkle: bool,
+) {
+  commands.spawn((
+    Particle {
+      velocity,
+      timer: Timer::from_seconds(lifetime, TimerMode::Once),
+      size,
+      sparkle,
+    },
+    SpriteBundle {
+      sprite: Sprite {
+        color,
+        custom_size: Some(Vec2::splat(size)),
+        ..default()
+      },
+      transform: Transform::from_translation(position.extend(COIN_Z + 1.0)),
+      ..default()
+    },
+  ));
+}
+
+fn update_particles(
+  mut commands: Commands,
+  time: Res<Time>,
+  mut query: Query<(Entity, &mut Particle, &mut Sprite, &mut Transform)>,
+) {
+  let dt = time.delta_seconds();
+  for (entity, mut particle, mut sprite, mut transform) in &mut query {
+    if particle.timer.tick(time.delta()).finished() {
+      commands.entity(entity).despawn();
+      continue;
+    }
+    transform.translation += (particle.velocity * dt).extend(0.0);
+    // air drag, so bursts slow to a stop instead of flying off the board
+    particle.velocity *= (1.0 - 4.0 * dt).max(0.0);
+    let left = particle.timer.percent_left();
+    sprite.color.set_a(left);
+    if particle.sparkle {
+      let flicker = (particle.timer.elapsed_secs() * 30.0).sin().abs();
+      sprite.custom_size = Some(Vec2::splat(particle.size * (0.4 + 0.6 * flicker)));
+    }
+  }
+}
+
+/// Finds the coins waiting to pop, once their sprites exist.
+fn start_pops(
+  mut commands: Commands,
+  mut pending_pops: ResMut<PendingPops>,
+  obj_query: Query<(Entity, &Player, &ObjId)>,
+) {
+  for (player, id, strength) in pending_pops.0.drain(..) {
+    let found = obj_query
+      .iter()
+      .find(|&(_, &obj_player, obj_id)| obj_player == player && obj_id.0 == id);
+    // the coin may have merged again already, which is a show of its own
+    if let Some((entity, _, _)) = found {
+      commands.entity(entity).insert(Pop {
+        timer: Timer::from_seconds(0.25, TimerMode::Once),
+        strength,
+      });
+    }
+  }
+}
+
+fn update_pops(
+  mut commands: Commands,
+  time: Res<Time>,
+  render_info: Res<RenderInfo>,
+  mut query: Query<(Entity, &mut Pop, &mut Transform)>,
+) {
+  let scale = render_info.coin_size / COIN_SIZE_PX;
+  for (entity, mut pop, mut transform) in &mut query {
+    let swell = if pop.timer.tick(time.delta()).finished() {
+      commands.entity(entity).remove::<Pop>();
+      0.0
+    } else {
+      pop.strength * (pop.timer.percent() * PI).sin()
+    };
+    transform.scale = Vec3::splat(scale * (1.0 + swell));
+  }
+}
+
+fn cleanup_effects(
+  mut commands: Commands,
+  mut pending_pops: ResMut<PendingPops>,
+  query: Query<Entity, With<Particle>>,
+) {
+  pending_pops.0.clear();
+  for entity in &query {
+    commands.entity(entity).despawn();
+  }
+}
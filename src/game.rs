@@ -1,21 +1,33 @@
-use std::cmp::Ordering;
 use std::time::Duration;
 
 use bevy::prelude::shape::Quad;
 use bevy::sprite::MaterialMesh2dBundle;
 use bevy::time::Stopwatch;
-use bevy::transform::commands;
-use bevy::utils::HashSet;
+use bevy::utils::HashMap;
 use bevy::{prelude::*, window::WindowResolution};
-use devcaders::{DevcadeControls, Player};
-// use itertools::Itertools;
-use rand::distributions::WeightedIndex;
 use rand::prelude::*;
-
+use serde::{Deserialize, Serialize};
+
+use crate::board::{random_row, BoardEvent, BoardState, CoinId, Position};
+use crate::coin_set::Coins;
+use crate::coins::{CoinSet, ObjType};
+use crate::cpu::Cpu;
+use crate::danger::Danger;
+use crate::input::{Button, GameInput, Player};
+use crate::replay::{Replay, ReplayAction, ReplayMode};
+use crate::rng::GameRng;
+use crate::rules::Rules;
+use crate::score::Score;
+use crate::settings::Settings;
+use crate::speed::{RowTimer, SpeedCurve};
+use crate::tween::{Motion, Tween, TweenSpeeds};
+use crate::versus::IncomingAttack;
 use crate::{consts::*, GameInfo, RenderInfo};
 
-#[derive(PartialEq, Eq)]
-enum ActionType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionType {
+  MoveLeft,
+  MoveRight,
   CoinPull,
   CoinPush,
   NewRow,
@@ -23,53 +35,122 @@ enum ActionType {
 
 struct LoseEvent(Player);
 
-struct MergeEvent {
-  player: Player,
-  position: Position,
+pub struct MergeEvent {
+  pub player: Player,
+  pub position: Position,
+  pub obj_type: ObjType,
+  pub count: usize,
+  /// 0 for a merge caused directly by a push, +1 for each chain reaction after it.
+  pub chain: u32,
+  /// The coin the merge made, unless `obj_type` is the top denomination.
+  pub new_coin: Option<CoinId>,
 }
 
-struct GameActionEvent {
-  player: Player,
-  action_type: ActionType,
+/// An `EraseItem` cleared `count` coins of `obj_type` from `player`'s board.
+pub struct EraseEvent {
+  pub player: Player,
+  pub position: Position,
+  pub obj_type: ObjType,
+  pub count: usize,
+  pub chain: u32,
+}
+
+/// `player`'s girl picked up `count` coins of `obj_type` from `col`. A pull that got nothing
+/// sends none.
+pub struct PullEvent {
+  pub player: Player,
+  pub col: i32,
+  pub obj_type: ObjType,
+  pub count: usize,
+}
+
+/// `player`'s girl put `count` coins of `obj_type` down into `col`.
+pub struct PushEvent {
+  pub player: Player,
+  pub col: i32,
+  pub obj_type: ObjType,
+  pub count: usize,
 }
 
+pub struct GameActionEvent {
+  pub player: Player,
+  pub action_type: ActionType,
+}
+
+/// Systems that send `GameActionEvent`s. Actions are recorded and applied after all of them.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SendActions;
+
+/// Where `GameActionEvent`s are applied to the boards. Anything else that touches what the next
+/// action does, like the garbage queued for a new row, goes after it so replays play out the same.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApplyActions;
+
+/// Frames since the game started. Replays are keyed on it.
+#[derive(Resource, Default)]
+struct GameTick(u32);
+
 #[derive(Component, Clone)]
 struct InputTimer {
   timer: Timer,
 }
 
-#[derive(Component, Clone)]
-struct Inventory {
-  obj_count: i32,
-  obj_type: ObjType,
+/// The rules-side state of a player's board. `BoardObj` sprites are kept in sync with it.
+#[derive(Component, Deref, DerefMut)]
+pub struct Board(BoardState);
+
+/// The row that lands on a player's board next. It's dealt from their `GameRng` stream one row
+/// ahead, so it can be shown before it arrives.
+#[derive(Component, Deref)]
+pub struct NextRow(Vec<ObjType>);
+
+impl NextRow {
+  fn deal(rng: &mut StdRng, coin_set: &CoinSet, width: i32) -> Self {
+    NextRow(random_row(rng, coin_set, width))
+  }
+
+  /// Hands over the row to insert and deals the one after it.
+  fn advance(&mut self, rng: &mut StdRng, coin_set: &CoinSet, width: i32) -> Vec<ObjType> {
+    std::mem::replace(&mut self.0, random_row(rng, coin_set, width))
+  }
 }
 
 #[derive(Component, Clone)]
-struct CoinGirl;
+pub struct CoinGirl;
 impl CoinGirl {
-  fn spawn(commands: &mut Commands, player: Player, render_info: &Res<RenderInfo>) -> Entity {
+  fn spawn(
+    commands: &mut Commands,
+    player: Player,
+    board: BoardState,
+    next_row: NextRow,
+    row_timer: RowTimer,
+    render_info: &Res<RenderInfo>,
+  ) -> Entity {
     let mut timer = InputTimer {
       timer: Timer::from_seconds(1.0, TimerMode::Once),
     };
     timer.timer.pause();
+    let position = Position {
+      col: board.width() / 2,
+      row: board.height() - 1,
+    };
+    let translation = render_info.obj_translate(player, position.col, position.row);
     commands
       .spawn((
         CoinGirl,
         player,
-        Position {
-          col: BOARD_DIM.0 / 2,
-          row: BOARD_DIM.1 - 1,
-        },
+        GridPos(position),
         timer,
-        Inventory {
-          obj_count: 0,
-          obj_type: ObjType::One,
-        },
+        Board(board),
+        next_row,
+        row_timer,
+        Score::default(),
+        IncomingAttack::default(),
+        Danger::default(),
+        Tween::at(translation),
         SpriteBundle {
           transform: Transform {
-            translation: render_info
-              .obj_translate(player, BOARD_DIM.0 / 2, BOARD_DIM.1 - 1)
-              .extend(GIRL_Z),
+            translation: translation.extend(GIRL_Z),
             scale: Vec3::splat(render_info.coin_size * GIRL_SIZE_FACTOR),
             ..default()
           },
@@ -84,71 +165,56 @@ impl CoinGirl {
   }
 }
 
-#[derive(PartialOrd, Hash, PartialEq, Eq, Debug, Component, Clone, Copy)]
-struct Position {
-  pub col: i32,
-  pub row: i32,
-}
-impl Ord for Position {
-  fn cmp(&self, other: &Self) -> Ordering {
-    if self == other {
-      return Ordering::Equal;
-    }
-    if self.col < other.col {
-      return Ordering::Less;
-    } else if self.row > other.row {
-      return Ordering::Less;
-    }
-    return Ordering::Greater;
-  }
-}
+/// Where a girl or `BoardObj` is on her player's board.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Deref, DerefMut)]
+pub struct GridPos(pub Position);
 
-#[derive(Component, Clone, PartialEq, Eq)]
-struct Owned(bool);
+/// Links a `BoardObj` sprite to the coin it shows in its player's `Board`.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct ObjId(pub CoinId);
 
+/// A coin or item sprite on a board.
 #[derive(Component, Clone)]
-struct BoardObj;
+struct BoardObj {
+  obj_type: ObjType,
+  /// The girl is carrying it.
+  held: bool,
+}
 
 impl BoardObj {
   fn spawn(
     commands: &mut Commands,
+    id: CoinId,
     obj_type: ObjType,
+    sprite: &str,
     col: i32,
     row: i32,
+    held: bool,
     player: Player,
     asset_server: &Res<AssetServer>,
     render_info: &Res<RenderInfo>,
   ) -> Entity {
+    let translation = render_info.obj_translate(player, col, row);
     commands
       .spawn((
-        BoardObj,
-        Position { col, row },
-        obj_type,
+        BoardObj { obj_type, held },
+        ObjId(id),
+        GridPos(Position { col, row }),
         player,
+        Motion::default(),
+        Tween::at(translation),
         SpriteBundle {
           transform: Transform {
-            translation: render_info
-              .obj_translate(player, col, row) //(top_left_coin_pos + Vec2::new(col as f32, -(row as f32)) * coin_size)
-              .extend(COIN_Z),
+            translation: translation.extend(COIN_Z),
             scale: Vec3::splat(render_info.coin_size / COIN_SIZE_PX),
             ..default()
           },
-          texture: asset_server.load(obj_type.get_path()),
+          texture: asset_server.load(sprite),
           ..default()
         },
-        Owned(false),
       ))
       .id()
   }
-
-  //   fn other_player(&self, render_info: &Res<RenderInfo>) -> Self {
-  //     let mut obj = self.clone();
-  //     obj.player = Player::P2;
-  //     obj.sprite_bundle.transform.translation = render_info
-  //       .obj_translate(Player::P2, obj.position.col, obj.position.row)
-  //       .extend(COIN_Z);
-  //     obj
-  //   }
 }
 
 #[derive(Component)]
@@ -164,6 +230,9 @@ impl Plugin for GamePlugin {
       .add_event::<GameActionEvent>()
       .add_event::<LoseEvent>()
       .add_event::<MergeEvent>()
+      .add_event::<EraseEvent>()
+      .add_event::<PullEvent>()
+      .add_event::<PushEvent>()
       .add_systems((
         setup_game.in_schedule(OnEnter(AppState::Game)),
         time.in_set(OnUpdate(AppState::Game)),
@@ -174,32 +243,31 @@ impl Plugin for GamePlugin {
       ))
       .add_systems((
         check_lose
-          .before(game_input)
+          .after(game_action_handler)
           .in_set(OnUpdate(AppState::Game)),
         lose_input.in_set(OnUpdate(AppState::Lost)),
       ))
       .add_systems((
-        game_input.before(render).in_set(OnUpdate(AppState::Game)),
-        coin_pull_handler
-          .after(game_input)
+        advance_tick
+          .before(SendActions)
           .in_set(OnUpdate(AppState::Game)),
-        coin_push_handler
-          .after(game_input)
+        new_row.before(SendActions).in_set(OnUpdate(AppState::Game)),
+        game_input
+          .before(render)
+          .in_set(SendActions)
           .in_set(OnUpdate(AppState::Game)),
-        new_row_handler
-          .after(game_input)
+        record_actions
+          .after(SendActions)
           .in_set(OnUpdate(AppState::Game)),
-        merge_handler
-          .after(coin_push_handler)
-          .before(render)
+        game_action_handler
+          .after(SendActions)
+          .in_set(ApplyActions)
           .in_set(OnUpdate(AppState::Game)),
-        coin_fall
-          .before(new_row_handler)
+        sync_board_objs
+          .after(game_action_handler)
           .before(render)
           .in_set(OnUpdate(AppState::Game)),
-      ))
-      .add_system(new_row.in_schedule(CoreSchedule::FixedUpdate))
-      .insert_resource(FixedTime::new_from_secs(4.5));
+      ));
   }
 }
 
@@ -209,65 +277,61 @@ fn time(time: Res<Time>, mut girl_query: Query<&mut InputTimer>) {
   }
 }
 
+/// Points each sprite's `Tween` at its grid position. A resized window puts everything straight
+/// in place.
 fn render(
-  mut query: Query<(&mut Transform, &Position, &Player)>,
+  mut query: Query<(&mut Tween, &GridPos, &Player, Option<&Motion>)>,
   game_state: Res<GameInfo>,
   render_info: Res<RenderInfo>,
+  speeds: Res<TweenSpeeds>,
 ) {
-  for (mut transform, position, &player) in &mut query {
-    let z_index = transform.translation.z;
-    transform.translation = render_info
-      .obj_translate(player, position.col, position.row)
-      .extend(z_index);
+  for (mut tween, position, &player, motion) in &mut query {
+    let target = render_info.obj_translate(player, position.col, position.row);
+    if render_info.is_changed() {
+      tween.snap(target);
+    } else if tween.target() != target {
+      let motion = motion.copied().unwrap_or_default();
+      tween.retarget(target, motion, render_info.coin_size, &speeds);
+    }
   }
-  // // render girl
-  // for (mut girl_transform, girl) in set.p1().iter_mut() {
-  //   girl_transform.translation = render_info
-  //     .obj_translate(girl.player, girl.col, BOARD_DIM.1 - 1)
-  //     .extend(GIRL_Z);
-  // }
-
-  // // render coins
-  // for (mut transform, obj) in set.p0().iter_mut() {
-  //   transform.translation = render_info
-  //     .obj_translate(obj.player, obj.col, obj.row)
-  //     .extend(GIRL_Z);
-  // }
+}
+
+fn advance_tick(mut tick: ResMut<GameTick>) {
+  tick.0 += 1;
 }
 
 fn game_input(
-  input: DevcadeControls,
-  mut girl_query: Query<
-    (&Player, &mut Position, &mut InputTimer),
-    (With<CoinGirl>, Without<BoardObj>),
-  >,
-  mut obj_query: Query<(&Player, &mut Position, &Owned), With<BoardObj>>,
+  input: Res<GameInput>,
+  mut girl_query: Query<(&Player, &mut InputTimer), (With<CoinGirl>, Without<Cpu>)>,
   mut action_writer: EventWriter<GameActionEvent>,
-  time_step: Res<FixedTime>,
+  mut replay_mode: ResMut<ReplayMode>,
+  tick: Res<GameTick>,
 ) {
-  for (&player, mut position, mut timer) in &mut girl_query {
-    if input.just_pressed(player, devcaders::Button::StickLeft) {
-      if position.col > 0 {
-        position.col -= 1;
-        for (&coin_player, mut coin_pos, owned) in &mut obj_query {
-          if player == coin_player && owned.0 {
-            coin_pos.col -= 1;
-          }
-        }
-      }
+  if replay_mode.is_playback() {
+    for action in replay_mode.playback_actions(tick.0) {
+      action_writer.send(GameActionEvent {
+        player: action.player,
+        action_type: action.action_type,
+      });
     }
-    if input.just_pressed(player, devcaders::Button::StickRight) {
-      if position.col < 6 {
-        position.col += 1;
-        for (&coin_player, mut coin_pos, owned) in &mut obj_query {
-          if player == coin_player && owned.0 {
-            coin_pos.col += 1;
-          }
-        }
-      }
+    return;
+  }
+
+  for (&player, mut timer) in &mut girl_query {
+    if input.just_pressed(player, Button::StickLeft) {
+      action_writer.send(GameActionEvent {
+        player,
+        action_type: ActionType::MoveLeft,
+      });
+    }
+    if input.just_pressed(player, Button::StickRight) {
+      action_writer.send(GameActionEvent {
+        player,
+        action_type: ActionType::MoveRight,
+      });
     }
 
-    if input.just_pressed(player, devcaders::Button::StickDown) {
+    if input.just_pressed(player, Button::StickDown) {
       // timer is running and finished
       if !timer.timer.paused() && !timer.timer.finished() {
         // new row
@@ -283,13 +347,13 @@ fn game_input(
       }
     }
 
-    if input.just_pressed(player, devcaders::Button::A1) {
+    if input.just_pressed(player, Button::A1) {
       action_writer.send(GameActionEvent {
         player,
         action_type: ActionType::CoinPull,
       });
     }
-    if input.just_pressed(player, devcaders::Button::A2) {
+    if input.just_pressed(player, Button::A2) {
       action_writer.send(GameActionEvent {
         player,
         action_type: ActionType::CoinPush,
@@ -298,316 +362,231 @@ fn game_input(
   }
 }
 
-fn coin_push_handler(
+fn record_actions(
   mut events: EventReader<GameActionEvent>,
-  mut coin_query: Query<(Entity, &mut Position, &Player, &ObjType, &mut Owned), With<BoardObj>>,
-  mut girl_query: Query<(&mut Inventory, &Player, &Position), (With<CoinGirl>, Without<BoardObj>)>,
-  mut commands: Commands,
-  asset_server: Res<AssetServer>,
-  render_info: Res<RenderInfo>,
-  mut merge_event_writer: EventWriter<MergeEvent>,
+  mut replay_mode: ResMut<ReplayMode>,
+  tick: Res<GameTick>,
 ) {
+  let ReplayMode::Record(replay) = replay_mode.as_mut() else {
+    return;
+  };
   for ev in events.iter() {
-    if ev.action_type != ActionType::CoinPush {
-      return;
-    }
-    for (mut inventory, &girl_player, girl_pos) in &mut girl_query {
-      if girl_player != ev.player {
-        continue;
-      }
-      if inventory.obj_count == 0 {
-        continue;
-      }
-
-      let mut max_row = 0;
-      let mut same_coin_types = Vec::new();
-      for (entity, coin_pos, &coin_player, &obj_type, owned) in &coin_query {
-        if coin_player != ev.player || owned.0 {
-          continue;
-        }
-        if obj_type == inventory.obj_type {
-          same_coin_types.push((entity, coin_pos.clone()));
-        }
-        if coin_pos.col != girl_pos.col {
-          continue;
-        }
-        if coin_pos.row + 1 > max_row {
-          max_row = coin_pos.row + 1;
-        }
-      }
-
-      let placed_coin_pos = Position {
-        col: girl_pos.col,
-        row: girl_pos.row - (BOARD_DIM.1 - max_row - inventory.obj_count),
-      };
-      // move inv back to board
-      for (entity, mut coin_pos, &coin_player, &obj_type, mut owned) in &mut coin_query {
-        if coin_player != ev.player || !owned.0 {
-          continue;
-        }
-        coin_pos.row -= BOARD_DIM.1 - max_row - inventory.obj_count;
-        owned.0 = false;
-        same_coin_types.push((entity, coin_pos.to_owned().clone()));
-      }
-      inventory.obj_count = 0;
-      merge_event_writer.send(MergeEvent {
-        player: ev.player,
-        position: placed_coin_pos,
-      });
-    }
-  }
-}
-
-fn get_connected(
-  coin_list: &Vec<(Entity, Position, &Player, ObjType)>,
-  position: Position,
-  player: Player,
-) -> Option<(ObjType, Vec<(Entity, Position)>)> {
-  // println!("{:?}", position);
-  // for c in coin_query {
-  //   if *c.2 == player {//     println!("{:?} {:?}", c.1, c.3);
-  //   }
-  // }
-  let coin = coin_list
-    .iter()
-    .find(|&(_, pos, &q_player, _)| *pos == position && q_player == player)?
-    .to_owned();
-  let init_obj_type = coin.3.to_owned();
-  let mut set = HashSet::new();
-  let mut stack = Vec::new();
-  stack.push((coin.0, position));
-  while !stack.is_empty() {
-    let (coin_entity, coin_pos) = stack.pop().unwrap();
-    if !set.contains(&(coin_entity, coin_pos)) {
-      if let Some(adj_coin) = coin_list.iter().find(|(_, pos, _, obj_type)| {
-        *obj_type == init_obj_type && pos.col == coin_pos.col - 1 && pos.row == coin_pos.row
-      }) {
-        stack.push((adj_coin.0.to_owned(), adj_coin.1.to_owned()));
-      }
-      if let Some(adj_coin) = coin_list.iter().find(|(_, pos, _, obj_type)| {
-        *obj_type == init_obj_type && pos.col == coin_pos.col + 1 && pos.row == coin_pos.row
-      }) {
-        stack.push((adj_coin.0.to_owned(), adj_coin.1.to_owned()));
-      }
-      if let Some(adj_coin) = coin_list.iter().find(|(_, pos, _, obj_type)| {
-        *obj_type == init_obj_type && pos.col == coin_pos.col && pos.row == coin_pos.row - 1
-      }) {
-        stack.push((adj_coin.0.to_owned(), adj_coin.1.to_owned()));
-      }
-      if let Some(adj_coin) = coin_list.iter().find(|(_, pos, _, obj_type)| {
-        *obj_type == init_obj_type && pos.col == coin_pos.col && pos.row == coin_pos.row + 1
-      }) {
-        stack.push((adj_coin.0.to_owned(), adj_coin.1.to_owned()));
-      }
-      set.insert((coin_entity, coin_pos));
-    }
+    replay.actions.push(ReplayAction {
+      tick: tick.0,
+      player: ev.player,
+      action_type: ev.action_type,
+    });
   }
-  Some((init_obj_type, set.into_iter().collect()))
 }
 
-fn coin_fall(mut coin_query: Query<(&mut Position, &Owned, &Player), With<BoardObj>>) {
-  for player in [Player::P1, Player::P2] {
-    let mut coins_to_shift = Vec::new();
-    let mut coin_board: [[bool; BOARD_DIM.1 as usize]; BOARD_DIM.0 as usize] =
-      [[false; BOARD_DIM.1 as usize]; BOARD_DIM.0 as usize];
-    for (coin_pos, owned, &coin_player) in &mut coin_query {
-      if owned.0 || coin_player != player {
+/// Applies each action to the acting player's girl and `Board`, and lets the board settle.
+fn game_action_handler(
+  mut events: EventReader<GameActionEvent>,
+  mut girl_query: Query<
+    (
+      &Player,
+      &mut GridPos,
+      &mut Board,
+      &mut NextRow,
+      &mut IncomingAttack,
+      &mut RowTimer,
+    ),
+    With<CoinGirl>,
+  >,
+  mut game_rng: ResMut<GameRng>,
+  mut merge_writer: EventWriter<MergeEvent>,
+  mut erase_writer: EventWriter<EraseEvent>,
+  mut pull_writer: EventWriter<PullEvent>,
+  mut push_writer: EventWriter<PushEvent>,
+) {
+  for ev in events.iter() {
+    for (&player, mut girl_pos, mut board, mut next_row, mut incoming, mut row_timer) in
+      &mut girl_query
+    {
+      if player != ev.player {
         continue;
       }
-      if coin_pos.row >= BOARD_DIM.1 {
-        return;
-      }
-      let row = coin_pos.row as usize;
-      let col = coin_pos.col as usize;
-      coin_board[col][row] = true;
-    }
-    for (col, column) in coin_board.iter().enumerate() {
-      let mut gap_size: usize = 0;
-      for (row, &coin) in column.iter().enumerate() {
-        if !coin {
-          gap_size += 1;
+      let mut board_events = match ev.action_type {
+        ActionType::MoveLeft => {
+          girl_pos.col = (girl_pos.col - 1).max(0);
+          Vec::new()
+        }
+        ActionType::MoveRight => {
+          girl_pos.col = (girl_pos.col + 1).min(board.width() - 1);
+          Vec::new()
         }
-        if coin && gap_size > 0 {
-          coins_to_shift.push((
-            Position {
-              col: col as i32,
-              row: row as i32,
-            },
-            gap_size,
-          ));
+        ActionType::CoinPull => board.pull(girl_pos.col),
+        ActionType::CoinPush => board.push(girl_pos.col),
+        ActionType::NewRow => {
+          // queued garbage lands along with the regular row
+          let mut row_events = Vec::new();
+          for _ in 0..std::mem::take(&mut **incoming) {
+            let row = random_row(game_rng.garbage(player), board.coin_set(), board.width());
+            row_events.extend(board.insert_row(&row));
+          }
+          let row = next_row.advance(game_rng.player(player), board.coin_set(), board.width());
+          row_events.extend(board.insert_row(&row));
+          row_timer.restart();
+          row_events
+        }
+      };
+      board_events.extend(board.settle());
+      for board_event in board_events {
+        match board_event {
+          BoardEvent::Merged {
+            obj_type,
+            coins,
+            upgrade,
+            chain,
+          } => merge_writer.send(MergeEvent {
+            player,
+            position: upgrade.map_or(coins[0], |(_, pos)| pos),
+            obj_type,
+            count: coins.len(),
+            chain,
+            new_coin: upgrade.map(|(coin, _)| coin.id),
+          }),
+          BoardEvent::Erased {
+            position,
+            obj_type,
+            coins,
+            chain,
+          } => erase_writer.send(EraseEvent {
+            player,
+            position,
+            obj_type,
+            count: coins.len(),
+            chain,
+          }),
+          BoardEvent::Pulled {
+            col,
+            obj_type,
+            count,
+          } => pull_writer.send(PullEvent {
+            player,
+            col,
+            obj_type,
+            count,
+          }),
+          BoardEvent::Pushed {
+            col,
+            obj_type,
+            count,
+          } => push_writer.send(PushEvent {
+            player,
+            col,
+            obj_type,
+            count,
+          }),
+          _ => (),
         }
       }
     }
-    if !coins_to_shift.is_empty() {}
-    for (pos, gap) in coins_to_shift {
-      coin_query
-        .iter_mut()
-        .find(|(coin_pos, owned, &coin_player)| {
-          player == coin_player && !owned.0 && coin_pos.col == pos.col && coin_pos.row == pos.row
-        })
-        .unwrap()
-        .0
-        .row -= gap as i32;
-    }
   }
 }
 
-fn merge_handler(
-  mut merge_events: EventReader<MergeEvent>,
-  coin_query: Query<(Entity, &Position, &Player, &ObjType), With<BoardObj>>,
+/// Spawns, moves and despawns `BoardObj` sprites to match each player's `Board`. Held coins are
+/// stacked on top of the girl.
+fn sync_board_objs(
   mut commands: Commands,
+  girl_query: Query<(&Player, &GridPos, &Board), With<CoinGirl>>,
+  mut obj_query: Query<
+    (
+      Entity,
+      &Player,
+      &ObjId,
+      &mut BoardObj,
+      &mut GridPos,
+      &mut Motion,
+      &mut Handle<Image>,
+    ),
+    Without<CoinGirl>,
+  >,
   asset_server: Res<AssetServer>,
   render_info: Res<RenderInfo>,
 ) {
-  for ev in merge_events.iter() {
-    let coin_list: Vec<(Entity, Position, &Player, ObjType)> = coin_query
-      .iter()
-      .filter(|&(_, _, &player, _)| player == ev.player)
-      .map(|x| (x.0, x.1.to_owned(), x.2, x.3.to_owned()))
+  for (&player, girl_pos, board) in &girl_query {
+    let mut wanted: HashMap<CoinId, (Position, ObjType, bool)> = board
+      .coins()
+      .map(|(pos, coin)| (coin.id, (pos, coin.obj_type, false)))
       .collect();
-    let mut pos = ev.position.clone();
-    let (obj_type, coins) = get_connected(&coin_list, pos, ev.player).unwrap();
-    let mut coin_count = coins.len();
-    // while coin_count >= obj_type.get_merge_count() {
-    if coin_count >= obj_type.get_merge_count() {
-      // actually merge
-
-      // create new coin
-      let new_pos = coins
-        .iter()
-        .max_by(|&(_, pos_a), &(_, pos_b)| pos_a.cmp(pos_b))
-        .unwrap()
-        .to_owned()
-        .1;
-      if let Some(new_type) = obj_type.get_upgrade() {
-        BoardObj::spawn(
-          &mut commands,
-          new_type,
-          new_pos.col,
-          new_pos.row,
-          ev.player,
-          &asset_server,
-          &render_info,
-        );
-      }
-      // remove existing coins
-      for (entity, _) in coins {
-        commands.entity(entity).despawn();
-      }
+    for (i, coin) in board.held().iter().enumerate() {
+      let pos = Position {
+        col: girl_pos.col,
+        row: board.height() - 1 - i as i32,
+      };
+      wanted.insert(coin.id, (pos, coin.obj_type, true));
     }
-  }
-}
 
-fn coin_pull_handler(
-  mut events: EventReader<GameActionEvent>,
-  mut girl_query: Query<(&Position, &Player, &mut Inventory), (With<CoinGirl>, Without<BoardObj>)>,
-  mut coin_query: Query<
-    (Entity, &mut Position, &Player, &ObjType, &mut Owned),
-    (With<BoardObj>, Without<CoinGirl>),
-  >,
-  asset_server: Res<AssetServer>,
-  render_info: Res<RenderInfo>,
-  mut commands: Commands,
-) {
-  for ev in events.iter() {
-    if ev.action_type != ActionType::CoinPull {
-      return;
-    }
-    for (girl_pos, &girl_player, mut inventory) in &mut girl_query {
-      if ev.player != girl_player {
+    for (entity, &obj_player, id, mut obj, mut pos, mut motion, mut texture) in &mut obj_query {
+      if obj_player != player {
         continue;
       }
-      let mut coins = Vec::new();
-      for (entity, coin_pos, &coin_player, &obj, owned) in &mut coin_query {
-        if !owned.0 && girl_player == coin_player && girl_pos.col == coin_pos.col {
-          coins.push((coin_pos, obj, entity, owned));
-        }
+      let Some((new_pos, new_type, held)) = wanted.remove(&id.0) else {
+        commands.entity(entity).despawn();
+        continue;
+      };
+      if **pos != new_pos {
+        // closing a gap moves a coin up its own column; pulls and pushes slide
+        let falling = !held && !obj.held && new_pos.col == pos.col && new_pos.row < pos.row;
+        *motion = if falling { Motion::Fall } else { Motion::Slide };
+        **pos = new_pos;
       }
-      coins.sort_by(|(a, _, _, _), (b, _, _, _)| b.row.cmp(&a.row));
-      let first_coin = coins.first();
-      let mut valid_coin_pos = Vec::new();
-      if let Some(&(_, coin_type, _, _)) = first_coin {
-        if inventory.obj_count != 0 && coin_type != inventory.obj_type {
-          continue;
-        }
-        for (coin_pos, obj_type, entity, mut owned) in coins {
-          if coin_type != obj_type {
-            break;
-          }
-          valid_coin_pos.push((coin_pos, entity));
-          owned.0 = true;
-        }
-        let move_down_by = 12 - 1 - valid_coin_pos.first().unwrap().0.row - inventory.obj_count;
-        inventory.obj_type = coin_type;
-        inventory.obj_count += valid_coin_pos.len() as i32;
-        for (mut pos, entity) in valid_coin_pos {
-          pos.row += move_down_by;
-        }
+      if obj.held != held {
+        obj.held = held;
+      }
+      if obj.obj_type != new_type {
+        obj.obj_type = new_type;
+        *texture = asset_server.load(board.coin_set().sprite(new_type));
       }
     }
-  }
-}
 
-fn new_row(mut events: EventWriter<GameActionEvent>, game_info: Res<GameInfo>) {
-  events.send(GameActionEvent {
-    player: Player::P1,
-    action_type: ActionType::NewRow,
-  });
-  if game_info.players == 2 {
-    events.send(GameActionEvent {
-      player: Player::P2,
-      action_type: ActionType::NewRow,
-    });
+    for (id, (pos, obj_type, held)) in wanted {
+      BoardObj::spawn(
+        &mut commands,
+        id,
+        obj_type,
+        board.coin_set().sprite(obj_type),
+        pos.col,
+        pos.row,
+        held,
+        player,
+        &asset_server,
+        &render_info,
+      );
+    }
   }
 }
 
-fn new_row_handler(
-  mut events: EventReader<GameActionEvent>,
-  mut query: Query<(&mut Position, &Player, &Owned), With<BoardObj>>,
-  asset_server: Res<AssetServer>,
-  render_info: Res<RenderInfo>,
-  mut commands: Commands,
+fn new_row(
+  mut events: EventWriter<GameActionEvent>,
+  time: Res<Time>,
+  mut girl_query: Query<(&Player, &mut RowTimer, &Score), With<CoinGirl>>,
+  replay_mode: Res<ReplayMode>,
 ) {
-  for ev in events.iter() {
-    if ev.action_type == ActionType::NewRow {
-      // move existing coins down one
-      for (mut position, &player, owned) in &mut query {
-        if !owned.0 && player == ev.player {
-          position.row += 1;
-        }
-      }
-      // spawn a new row
-      let mut rng = thread_rng();
-      let dist = WeightedIndex::new(BOARD_OBJS.iter().map(|item| item.weight)).unwrap();
-      for col in 0..BOARD_DIM.0 {
-        let item = BOARD_OBJS[dist.sample(&mut rng)].clone();
-        BoardObj::spawn(
-          &mut commands,
-          item.obj_type,
-          col,
-          0,
-          ev.player,
-          &asset_server,
-          &render_info,
-        );
-      }
+  for (&player, mut row_timer, score) in &mut girl_query {
+    // played back games get their rows from the replay
+    if row_timer.tick(time.delta(), **score) && !replay_mode.is_playback() {
+      events.send(GameActionEvent {
+        player,
+        action_type: ActionType::NewRow,
+      });
     }
   }
 }
 
 fn check_lose(
   mut next_state: ResMut<NextState<AppState>>,
-  coin_query: Query<(&Position, &Player), With<BoardObj>>,
+  girl_query: Query<(&Player, &Board), With<CoinGirl>>,
   mut event_writer: EventWriter<LoseEvent>,
 ) {
-  for (coin_pos, &player) in &coin_query {
-    if coin_pos.row >= BOARD_DIM.1 {
+  for (&player, board) in &girl_query {
+    if board.is_lost() {
       next_state.set(AppState::Lost);
       event_writer.send(LoseEvent(player));
     }
   }
 }
-
 fn setup_lose_screen(
   mut commands: Commands,
   mut event_reader: EventReader<LoseEvent>,
@@ -619,7 +598,7 @@ fn setup_lose_screen(
     font: asset_server.load("Evogria.otf"),
   };
   for ev in &mut event_reader {
-    println!("{:?}", ev.0);
+    info!("{:?} lost", ev.0);
     commands.spawn((
       Text2dBundle {
         text: Text::from_section(
@@ -644,11 +623,9 @@ fn cleanup_lose_screen(mut commands: Commands, query: Query<Entity, With<UIText>
   }
 }
 
-fn lose_input(input: DevcadeControls, mut next_state: ResMut<NextState<AppState>>) {
-  if input.just_pressed(Player::P1, devcaders::Button::A4)
-    || input.just_pressed(Player::P2, devcaders::Button::A4)
-  {
-    next_state.set(AppState::Menu);
+fn lose_input(input: Res<GameInput>, mut next_state: ResMut<NextState<AppState>>) {
+  if input.just_pressed(Player::P1, Button::A4) || input.just_pressed(Player::P2, Button::A4) {
+    next_state.set(AppState::EnterInitials);
   }
 }
 
@@ -664,78 +641,112 @@ fn cleanup_game(
 #[derive(Component)]
 struct UIElement;
 
+/// The quad behind a player's board.
+#[derive(Component)]
+pub struct BoardBackground;
+
 fn setup_game(
   mut commands: Commands,
   mut materials: ResMut<Assets<ColorMaterial>>,
   mut meshes: ResMut<Assets<Mesh>>,
   asset_server: Res<AssetServer>,
   game_state: Res<GameInfo>,
+  rules: Res<Rules>,
+  coins: Res<Coins>,
+  settings: Res<Settings>,
   render_info: Res<RenderInfo>,
+  replay_mode: Option<Res<ReplayMode>>,
   window: Query<&mut Window>,
 ) {
   let resolution = &window.single().resolution;
-  let mut rng = thread_rng();
+  let mut game_rng = match replay_mode.as_deref() {
+    Some(ReplayMode::Playback { replay, .. }) => GameRng::new(replay.seed),
+    _ => {
+      let game_rng = GameRng::new(game_state.seed.unwrap_or_else(random));
+      commands.insert_resource(ReplayMode::Record(Replay::new(
+        game_rng.seed(),
+        game_state.players,
+        *rules,
+      )));
+      game_rng
+    }
+  };
+  info!("Seed: {}", game_rng.seed());
+  commands.insert_resource(GameTick::default());
 
-  let board_quad = get_board_quad(game_state.players, Player::P1, resolution);
+  let board_quad = get_board_quad(game_state.players, Player::P1, &rules, resolution);
 
   // Board
   commands.spawn((
     MaterialMesh2dBundle {
       mesh: meshes.add(board_quad.into()).into(),
-      material: materials.add(ColorMaterial::from(Color::rgb(0.5, 0.5, 0.5))),
+      material: materials.add(ColorMaterial::from(BOARD_COLOR)),
       transform: render_info.transform_p1,
       ..default()
     },
+    BoardBackground,
+    Player::P1,
     UIElement,
   ));
   if game_state.players == 2 {
     commands.spawn((
       MaterialMesh2dBundle {
         mesh: meshes.add(board_quad.into()).into(),
-        material: materials.add(ColorMaterial::from(Color::rgb(0.5, 0.5, 0.5))),
+        material: materials.add(ColorMaterial::from(BOARD_COLOR)),
         transform: render_info.transform_p2,
         ..default()
       },
+      BoardBackground,
+      Player::P2,
       UIElement,
     ));
   }
 
-  // Initial Coins
-  let dist = WeightedIndex::new(BOARD_OBJS.iter().map(|item| item.weight)).unwrap();
-  for row in 0..3 {
-    for col in 0..BOARD_DIM.0 {
-      let item = BOARD_OBJS[dist.sample(&mut rng)].clone();
-      BoardObj::spawn(
-        &mut commands,
-        item.obj_type,
-        col,
-        row,
-        Player::P1,
-        &asset_server,
-        &render_info,
-      );
-      if game_state.players == 2 {
-        BoardObj::spawn(
-          &mut commands,
-          item.obj_type,
-          col,
-          row,
-          Player::P2,
-          &asset_server,
-          &render_info,
-        );
-      }
+  // Initial Coins, then the first row to preview
+  let mut new_board = |player| {
+    let mut board = BoardState::new(rules.board_width, rules.board_height, coins.0.clone());
+    for _ in 0..3 {
+      board.insert_row(&random_row(
+        game_rng.player(player),
+        &coins,
+        rules.board_width,
+      ));
     }
-  }
+    let next_row = NextRow::deal(game_rng.player(player), &coins, rules.board_width);
+    (board, next_row)
+  };
 
   // Coin Girl
-  CoinGirl::spawn(&mut commands, Player::P1, &render_info);
+  let curve = SpeedCurve::for_players(game_state.players);
+  let (board, next_row) = new_board(Player::P1);
+  CoinGirl::spawn(
+    &mut commands,
+    Player::P1,
+    board,
+    next_row,
+    RowTimer::new(curve).with_speed_factor(settings.row_speed_factor(Player::P1)),
+    &render_info,
+  );
   if game_state.players == 2 {
-    CoinGirl::spawn(&mut commands, Player::P2, &render_info);
+    let (board, next_row) = new_board(Player::P2);
+    CoinGirl::spawn(
+      &mut commands,
+      Player::P2,
+      board,
+      next_row,
+      RowTimer::new(curve).with_speed_factor(settings.row_speed_factor(Player::P2)),
+      &render_info,
+    );
   }
+  commands.insert_resource(game_rng);
 }
 
-pub fn get_board_quad(players: usize, player: Player, resolution: &WindowResolution) -> Quad {
+pub fn get_board_quad(
+  players: usize,
+  player: Player,
+  rules: &Rules,
+  resolution: &WindowResolution,
+) -> Quad {
   let avail_x: f32;
   let avail_y: f32;
   if players == 2 {
@@ -753,90 +764,33 @@ pub fn get_board_quad(players: usize, player: Player, resolution: &WindowResolut
     avail_x = resolution.width();
     avail_y = resolution.height();
   }
-  let width: f32;
-  let height: f32;
   let margin = BOARD_MARGIN.evaluate(avail_x).unwrap();
-  if (avail_y * BOARD_DIM.0 as f32 / BOARD_DIM.1 as f32) < avail_x {
-    // height bound
-    height = avail_y - 2.0 * margin;
-    width = height / BOARD_DIM.1 as f32 * BOARD_DIM.0 as f32;
-  } else {
-    // width bound
-    width = avail_x - 2.0 * margin;
-    height = width * BOARD_DIM.1 as f32 / BOARD_DIM.0 as f32;
-  }
+  // leave room for the next row's preview above the board and as much below to keep it
+  // centered, and for the HUD at its right and as much at its left
+  let cols = rules.board_width as f32 + 2.0 * HUD_COLS;
+  let rows = rules.board_height as f32 + 2.0 * PREVIEW_SCALE;
+  let cell = ((avail_x - 2.0 * margin) / cols).min((avail_y - 2.0 * margin) / rows);
+  let width = cell * rules.board_width as f32;
+  let height = width / rules.aspect();
 
   Quad::new(Vec2::new(width, height))
-  // Rect::from_center_size(Vec2::ZERO, Vec2::new(width, height))
-  // Transform::default()
 }
 
+/// Where the center of `player`'s board goes. Boards are centered in their half of the screen.
 pub fn get_board_transform(
   players: usize,
   player: Player,
   resolution: &WindowResolution,
 ) -> Transform {
-  let avail_x: f32;
-  let avail_y: f32;
-  if players == 2 {
-    // figure out divide screen vertically or horizontally
-    if resolution.width() > resolution.height() {
-      // split horizontally
-      avail_x = resolution.width() / 2.0;
-      avail_y = resolution.height();
-      if player == Player::P1 {
-        return Transform::from_translation(Vec3::new(-resolution.width() / 4.0, 0.0, BOARD_Z));
-      } else {
-        return Transform::from_translation(Vec3::new(resolution.width() / 4.0, 0.0, BOARD_Z));
-      }
-    } else {
-      // split vertically
-      avail_x = resolution.width();
-      avail_y = resolution.height() / 2.0;
-      if player == Player::P1 {
-        return Transform::from_translation(Vec3::new(0.0, resolution.height() / 4.0, BOARD_Z));
-      } else {
-        return Transform::from_translation(Vec3::new(0.0, -resolution.height() / 4.0, BOARD_Z));
-      }
-    }
-  } else {
+  if players != 2 {
     return Transform::from_translation(Vec3::new(0.0, 0.0, BOARD_Z));
   }
-
-  let x: f32;
-  let y: f32;
-  let width: f32;
-  let height: f32;
-  let margin = BOARD_MARGIN.evaluate(avail_x).unwrap();
-  if (avail_y * BOARD_DIM.0 as f32 / BOARD_DIM.1 as f32) < avail_x {
-    // height bound
-    height = avail_y - 2.0 * margin;
-    width = height / BOARD_DIM.1 as f32 * BOARD_DIM.0 as f32;
-    y = 0.0;
-    if players == 2 {
-      x = if player == Player::P1 {
-        -avail_x / 4.0
-      } else {
-        avail_x / 4.0
-      };
-    } else {
-      x = 0.0;
-    }
+  let side = if player == Player::P1 { -1.0 } else { 1.0 };
+  if resolution.width() > resolution.height() {
+    // split horizontally
+    Transform::from_translation(Vec3::new(side * resolution.width() / 4.0, 0.0, BOARD_Z))
   } else {
-    // width bound
-    width = avail_x - 2.0 * margin;
-    height = width * BOARD_DIM.1 as f32 / BOARD_DIM.0 as f32;
-    x = 0.0;
-    if players == 2 {
-      y = if player == Player::P1 {
-        avail_y / 4.0
-      } else {
-        -avail_y / 4.0
-      };
-    } else {
-      y = 0.0;
-    }
+    // split vertically
+    Transform::from_translation(Vec3::new(0.0, -side * resolution.height() / 4.0, BOARD_Z))
   }
-  Transform::from_translation(Vec3::new(x, y, BOARD_Z)) //.with_scale(Vec3::new(width, height, 1.0))
-                                                        // Transform::default()
 }
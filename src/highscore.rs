@@ -0,0 +1,370 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::consts::AppState;
+use crate::cpu::Cpu;
+use crate::game::CoinGirl;
+use crate::input::{Button, GameInput, Player};
+use crate::replay::ReplayMode;
+use crate::score::Score;
+use crate::{ron_file, GameInfo};
+
+const MAX_ENTRIES: usize = 10;
+const ENTRIES_PER_PAGE: usize = 5;
+const PAGE_SECS: f32 = 4.0;
+const NAME_LEN: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+  pub name: String,
+  pub score: u32,
+  pub mode: String,
+  pub players: usize,
+  /// Seconds since the unix epoch.
+  pub date: u64,
+}
+
+/// The best scores on this machine, highest first.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct HighScoreTable {
+  pub entries: Vec<HighScore>,
+}
+
+impl HighScoreTable {
+  pub fn path() -> PathBuf {
+    crate::data_dir().join("high_scores.ron")
+  }
+
+  /// Reads the saved table. A missing file is an empty table.
+  pub fn load() -> Result<Self, String> {
+    ron_file::load_or_default(&Self::path(), "high scores")
+  }
+
+  pub fn save(&self) -> Result<(), String> {
+    ron_file::save(self, &Self::path())
+  }
+
+  pub fn qualifies(&self, score: u32) -> bool {
+    score > 0
+      && (self.entries.len() < MAX_ENTRIES
+        || self.entries.last().is_some_and(|entry| score > entry.score))
+  }
+
+  pub fn insert(&mut self, entry: HighScore) {
+    // ties keep the older entry first
+    let index = self.entries.partition_point(|e| e.score >= entry.score);
+    self.entries.insert(index, entry);
+    self.entries.truncate(MAX_ENTRIES);
+  }
+}
+
+pub struct HighScorePlugin;
+impl Plugin for HighScorePlugin {
+  fn build(&self, app: &mut App) {
+    let table = HighScoreTable::load().unwrap_or_else(|err| {
+      warn!("{err}");
+      HighScoreTable::default()
+    });
+    app
+      .insert_resource(table)
+      .init_resource::<FinalScores>()
+      .add_systems((
+        record_final_scores.in_schedule(OnExit(AppState::Game)),
+        setup_initials.in_schedule(OnEnter(AppState::EnterInitials)),
+        initials_input
+          .run_if(resource_exists::<InitialsEntry>())
+          .in_set(OnUpdate(AppState::EnterInitials)),
+        update_initials
+          .run_if(resource_exists::<InitialsEntry>())
+          .in_set(OnUpdate(AppState::EnterInitials)),
+        cleanup_screen.in_schedule(OnExit(AppState::EnterInitials)),
+        setup_high_scores.in_schedule(OnEnter(AppState::HighScores)),
+        high_scores.in_set(OnUpdate(AppState::HighScores)),
+        cleanup_screen.in_schedule(OnExit(AppState::HighScores)),
+      ));
+  }
+}
+
+/// Scores of the game that just ended that made it onto the table, waiting for initials.
+#[derive(Resource, Default)]
+struct FinalScores {
+  pending: Vec<(Player, u32)>,
+  mode: String,
+  players: usize,
+}
+
+#[derive(Resource)]
+struct InitialsEntry {
+  player: Player,
+  score: u32,
+  letters: [u8; NAME_LEN],
+  cursor: usize,
+}
+
+#[derive(Resource)]
+struct HighScorePage {
+  page: usize,
+  timer: Timer,
+}
+
+#[derive(Component)]
+struct HighScoreScreen;
+#[derive(Component)]
+struct InitialsText;
+#[derive(Component)]
+struct HighScoreList;
+
+fn record_final_scores(
+  game_info: Res<GameInfo>,
+  table: Res<HighScoreTable>,
+  replay_mode: Option<Res<ReplayMode>>,
+  mut final_scores: ResMut<FinalScores>,
+  girl_query: Query<(&Player, &Score), (With<CoinGirl>, Without<Cpu>)>,
+) {
+  final_scores.pending.clear();
+  final_scores.mode = game_info.mode_name().to_string();
+  final_scores.players = game_info.players;
+  // a played back game already had its chance, and practice games aren't ranked
+  if replay_mode.is_some_and(|mode| mode.is_playback()) || game_info.practice {
+    return;
+  }
+  for (&player, score) in &girl_query {
+    if table.qualifies(**score) {
+      final_scores.pending.push((player, **score));
+    }
+  }
+  final_scores
+    .pending
+    .sort_by_key(|&(player, _)| player != Player::P1);
+}
+
+fn setup_initials(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  mut final_scores: ResMut<FinalScores>,
+  mut next_state: ResMut<NextState<AppState>>,
+) {
+  if final_scores.pending.is_empty() {
+    next_state.set(AppState::Menu);
+    return;
+  }
+  let (player, score) = final_scores.pending.remove(0);
+  commands.insert_resource(InitialsEntry {
+    player,
+    score,
+    letters: [b'A'; NAME_LEN],
+    cursor: 0,
+  });
+
+  let font = asset_server.load("Evogria.otf");
+  let style = |font_size| TextStyle {
+    font: font.clone(),
+    font_size,
+    color: Color::WHITE,
+  };
+  commands
+    .spawn((screen_node(), HighScoreScreen))
+    .with_children(|parent| {
+      let player_name = match player {
+        Player::P1 => "Player 1",
+        Player::P2 => "Player 2",
+      };
+      parent.spawn(TextBundle::from_section(
+        format!("{player_name}\nNew High Score!\n{score}"),
+        style(60.0),
+      ));
+      parent.spawn((
+        TextBundle::from_sections((0..NAME_LEN).map(|_| TextSection::new("", style(100.0)))),
+        InitialsText,
+      ));
+      parent.spawn(TextBundle::from_section(
+        "Stick to pick a letter\nA1 to confirm",
+        style(30.0),
+      ));
+    });
+}
+
+fn initials_input(
+  input: Res<GameInput>,
+  final_scores: Res<FinalScores>,
+  mut entry: ResMut<InitialsEntry>,
+  mut table: ResMut<HighScoreTable>,
+  mut next_state: ResMut<NextState<AppState>>,
+) {
+  let player = entry.player;
+  let cursor = entry.cursor;
+  if input.just_released(player, Button::StickUp) {
+    entry.letters[cursor] = if entry.letters[cursor] == b'Z' {
+      b'A'
+    } else {
+      entry.letters[cursor] + 1
+    };
+  } else if input.just_released(player, Button::StickDown) {
+    entry.letters[cursor] = if entry.letters[cursor] == b'A' {
+      b'Z'
+    } else {
+      entry.letters[cursor] - 1
+    };
+  } else if input.just_released(player, Button::StickLeft) {
+    entry.cursor = cursor.saturating_sub(1);
+  } else if input.just_released(player, Button::StickRight) {
+    entry.cursor = (cursor + 1).min(NAME_LEN - 1);
+  } else if input.just_released(player, Button::A1) {
+    if cursor + 1 < NAME_LEN {
+      entry.cursor += 1;
+      return;
+    }
+    table.insert(HighScore {
+      name: String::from_utf8_lossy(&entry.letters).into_owned(),
+      score: entry.score,
+      mode: final_scores.mode.clone(),
+      players: final_scores.players,
+      date: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs(),
+    });
+    if let Err(err) = table.save() {
+      error!("{err}");
+    }
+    // re-entering the state asks the next player, or moves on to the menu
+    next_state.set(AppState::EnterInitials);
+  }
+}
+
+fn update_initials(entry: Res<InitialsEntry>, mut query: Query<&mut Text, With<InitialsText>>) {
+  if !entry.is_changed() {
+    return;
+  }
+  for mut text in &mut query {
+    for (i, section) in text.sections.iter_mut().enumerate() {
+      section.value = (entry.letters[i] as char).to_string();
+      section.style.color = if i == entry.cursor {
+        Color::YELLOW
+      } else {
+        Color::WHITE
+      };
+    }
+  }
+}
+
+fn setup_high_scores(mut commands: Commands, asset_server: Res<AssetServer>) {
+  let font = asset_server.load("Evogria.otf");
+  commands.insert_resource(HighScorePage {
+    page: 0,
+    timer: Timer::from_seconds(PAGE_SECS, TimerMode::Repeating),
+  });
+  commands
+    .spawn((screen_node(), HighScoreScreen))
+    .with_children(|parent| {
+      parent.spawn(TextBundle::from_section(
+        "High Scores",
+        TextStyle {
+          font: font.clone(),
+          font_size: 80.0,
+          color: Color::WHITE,
+        },
+      ));
+      parent.spawn((
+        TextBundle::from_section(
+          "",
+          TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::WHITE,
+          },
+        ),
+        HighScoreList,
+      ));
+    });
+}
+
+/// Shows a page of the table at a time, flipping through them until a button is pressed.
+fn high_scores(
+  time: Res<Time>,
+  input: Res<GameInput>,
+  table: Res<HighScoreTable>,
+  mut page: ResMut<HighScorePage>,
+  mut next_state: ResMut<NextState<AppState>>,
+  mut query: Query<&mut Text, With<HighScoreList>>,
+) {
+  for player in [Player::P1, Player::P2] {
+    if input.just_released(player, Button::A1) || input.just_released(player, Button::A4) {
+      next_state.set(AppState::Menu);
+    }
+  }
+
+  let pages = table.entries.len().div_ceil(ENTRIES_PER_PAGE).max(1);
+  if page.timer.tick(time.delta()).just_finished() {
+    page.page = (page.page + 1) % pages;
+  }
+  if !page.is_changed() {
+    return;
+  }
+  let text = if table.entries.is_empty() {
+    "No scores yet".to_string()
+  } else {
+    table
+      .entries
+      .iter()
+      .enumerate()
+      .skip(page.page * ENTRIES_PER_PAGE)
+      .take(ENTRIES_PER_PAGE)
+      .map(|(i, entry)| {
+        format!(
+          "{:>2}. {} {:>7} {} {}",
+          i + 1,
+          entry.name,
+          entry.score,
+          entry.mode,
+          format_date(entry.date)
+        )
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  };
+  for mut list in &mut query {
+    list.sections[0].value = text.clone();
+  }
+}
+
+fn cleanup_screen(mut commands: Commands, query: Query<Entity, With<HighScoreScreen>>) {
+  for entity in &query {
+    commands.entity(entity).despawn_recursive();
+  }
+  commands.remove_resource::<InitialsEntry>();
+  commands.remove_resource::<HighScorePage>();
+}
+
+fn screen_node() -> NodeBundle {
+  NodeBundle {
+    style: Style {
+      size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+      justify_content: JustifyContent::Center,
+      align_items: AlignItems::Center,
+      flex_direction: FlexDirection::Column,
+      gap: Size::height(Val::Px(40.0)),
+      ..default()
+    },
+    ..default()
+  }
+}
+
+/// `YYYY-MM-DD` for a unix timestamp, in UTC.
+fn format_date(secs: u64) -> String {
+  // Howard Hinnant's civil_from_days
+  let days = (secs / 86_400) as i64 + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days.rem_euclid(146_097);
+  let year_of_era =
+    (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let mp = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+  format!("{year:04}-{month:02}-{day:02}")
+}
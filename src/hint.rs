@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::board::{BoardState, Position};
+use crate::consts::*;
+use crate::cpu::Cpu;
+use crate::game::{Board, CoinGirl};
+use crate::input::Player;
+use crate::settings::Settings;
+use crate::{GameInfo, RenderInfo};
+
+/// Where the girl should pull from, if she needs to, and where she should push into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hint {
+  pub pull: Option<i32>,
+  pub push: i32,
+}
+
+/// Picks the pull and push that build the biggest group, going by the group the pushed coins
+/// would join before anything merges. Groups that reach their merge count come first.
+pub fn best_hint(board: &BoardState) -> Option<Hint> {
+  let mut pulls: Vec<Option<i32>> = (0..board.width()).map(Some).collect();
+  if !board.held().is_empty() {
+    pulls.push(None);
+  }
+  let mut best: Option<(Hint, usize)> = None;
+  for pull in pulls {
+    let mut pulled = board.clone();
+    if let Some(col) = pull {
+      if pulled.pull(col).is_empty() {
+        continue;
+      }
+    }
+    let Some(obj_type) = pulled.held_type() else {
+      continue;
+    };
+    let merge_count = board.coin_set().merge_count(obj_type);
+    for push in 0..board.width() {
+      // pushing straight back where the coins came from changes nothing
+      if pull == Some(push) {
+        continue;
+      }
+      let mut pushed = pulled.clone();
+      let landing = Position {
+        col: push,
+        row: pushed.column_height(push) + pushed.held().len() as i32 - 1,
+      };
+      pushed.push(push);
+      if pushed.is_lost() {
+        continue;
+      }
+      let Some((_, group)) = pushed.get_connected(landing) else {
+        continue;
+      };
+      // anything that merges beats anything that doesn't
+      let value = if group.len() >= merge_count {
+        group.len() + 100
+      } else {
+        group.len()
+      };
+      if best.is_none_or(|(_, best_value)| value > best_value) {
+        best = Some((Hint { pull, push }, value));
+      }
+    }
+  }
+  best.map(|(hint, _)| hint)
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum HintMarker {
+  Pull,
+  Push,
+}
+
+/// Tells the players a practice game won't go on the high score table.
+#[derive(Component)]
+struct HintNotice;
+
+pub struct HintPlugin;
+impl Plugin for HintPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems((
+      setup_hints.in_schedule(OnEnter(AppState::Game)),
+      update_hints.in_set(OnUpdate(AppState::Game)),
+      cleanup_hints.in_schedule(OnExit(AppState::Game)),
+    ));
+  }
+}
+
+/// Hints are only shown in practice games, so a ranked game never has them.
+fn setup_hints(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  game_info: Res<GameInfo>,
+  settings: Res<Settings>,
+) {
+  if !game_info.practice {
+    return;
+  }
+  commands.spawn((
+    TextBundle::from_section(
+      "Practice - this game won't be ranked",
+      TextStyle {
+        font: asset_server.load("Evogria.otf"),
+        font_size: 30.0,
+        color: Color::rgba(1.0, 1.0, 1.0, 0.6),
+      },
+    )
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        bottom: Val::Px(10.0),
+        left: Val::Px(10.0),
+        ..default()
+      },
+      ..default()
+    }),
+    HintNotice,
+  ));
+  if !settings.hints {
+    return;
+  }
+  for player in [Player::P1, Player::P2] {
+    for (marker, color) in [
+      (HintMarker::Pull, Color::rgba(0.2, 0.4, 1.0, 0.3)),
+      (HintMarker::Push, Color::rgba(0.2, 1.0, 0.4, 0.3)),
+    ] {
+      commands.spawn((
+        SpriteBundle {
+          sprite: Sprite {
+            color,
+            anchor: Anchor::TopCenter,
+            ..default()
+          },
+          visibility: Visibility::Hidden,
+          ..default()
+        },
+        marker,
+        player,
+      ));
+    }
+  }
+}
+
+/// Shades the hinted columns of each human player's board. Hints are only worked out again
+/// when the board changes.
+fn update_hints(
+  girl_query: Query<(&Player, Ref<Board>), (With<CoinGirl>, Without<Cpu>)>,
+  mut marker_query: Query<(
+    &HintMarker,
+    &Player,
+    &mut Sprite,
+    &mut Transform,
+    &mut Visibility,
+  )>,
+  render_info: Res<RenderInfo>,
+) {
+  for (&player, board) in &girl_query {
+    if !board.is_changed() && !render_info.is_changed() {
+      continue;
+    }
+    let hint = best_hint(&board);
+    for (&marker, &marker_player, mut sprite, mut transform, mut visibility) in &mut marker_query {
+      if marker_player != player {
+        continue;
+      }
+      let col = match (marker, hint) {
+        (
+          HintMarker::Pull,
+          Some(Hint {
+            pull: Some(col), ..
+          }),
+        ) => col,
+        (HintMarker::Push, Some(Hint { push, .. })) => push,
+        _ => {
+          *visibility = Visibility::Hidden;
+          continue;
+        }
+      };
+      *visibility = Visibility::Visible;
+      sprite.custom_size = Some(Vec2::new(
+        render_info.coin_size,
+        render_info.board_height as f32 * render_info.coin_size,
+      ));
+      transform.translation = (render_info.obj_translate(player, col, 0)
+        + Vec2::new(0.0, 0.5) * render_info.coin_size)
+        .extend(GIRL_Z - 0.5);
+    }
+  }
+}
+
+fn cleanup_hints(
+  mut commands: Commands,
+  query: Query<Entity, Or<(With<HintMarker>, With<HintNotice>)>>,
+) {
+  for entity in &query {
+    commands.entity(entity).despawn_recursive();
+  }
+}
@@ -0,0 +1,423 @@
+use std::path::{Path, PathBuf};
+
+use bevy::app::AppExit;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::replay::Replay;
+use crate::ron_file;
+
+/// One side of the cabinet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component, Serialize, Deserialize)]
+pub enum Player {
+  P1,
+  P2,
+}
+
+impl Player {
+  pub const ALL: [Player; 2] = [Player::P1, Player::P2];
+
+  fn index(self) -> usize {
+    match self {
+      Player::P1 => 0,
+      Player::P2 => 1,
+    }
+  }
+}
+
+/// The buttons the game uses, named after the cabinet's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Button {
+  StickLeft,
+  StickRight,
+  StickUp,
+  StickDown,
+  A1,
+  A2,
+  A3,
+  A4,
+  Menu,
+}
+
+impl Button {
+  pub const ALL: [Button; 9] = [
+    Button::StickLeft,
+    Button::StickRight,
+    Button::StickUp,
+    Button::StickDown,
+    Button::A1,
+    Button::A2,
+    Button::A3,
+    Button::A4,
+    Button::Menu,
+  ];
+
+  fn bit(self) -> u16 {
+    1 << self as u16
+  }
+}
+
+/// The buttons each player is holding this frame and held the frame before, for every system
+/// that reads input. Filled in once per frame, before `Update`, from the `InputSource`.
+#[derive(Resource, Debug, Default)]
+pub struct GameInput {
+  frame: u32,
+  pressed: [u16; 2],
+  last: [u16; 2],
+}
+
+impl GameInput {
+  /// The frame the presses were read on, counting from 1. `InputScript`s count in these.
+  pub fn frame(&self) -> u32 {
+    self.frame
+  }
+
+  pub fn pressed(&self, player: Player, button: Button) -> bool {
+    self.pressed[player.index()] & button.bit() != 0
+  }
+
+  pub fn just_pressed(&self, player: Player, button: Button) -> bool {
+    self.pressed(player, button) && self.last[player.index()] & button.bit() == 0
+  }
+
+  pub fn just_released(&self, player: Player, button: Button) -> bool {
+    !self.pressed(player, button) && self.last[player.index()] & button.bit() != 0
+  }
+
+  fn update(&mut self, pressed: impl Fn(Player, Button) -> bool) {
+    self.frame += 1;
+    self.last = self.pressed;
+    for player in Player::ALL {
+      self.pressed[player.index()] = Button::ALL
+        .into_iter()
+        .filter(|&button| pressed(player, button))
+        .fold(0, |bits, button| bits | button.bit());
+    }
+  }
+}
+
+/// Where `GameInput` comes from. Swap it to drive the game without anyone at the controls.
+#[derive(Resource, Debug, Default)]
+pub enum InputSource {
+  /// The cabinet, or the keyboard and gamepads.
+  #[default]
+  Hardware,
+  /// A timeline of presses, written by hand or recorded with `COINGIRL_RECORD_INPUT`. Once it
+  /// runs out nothing is pressed.
+  Script(InputScript),
+  /// A recorded game, played back from its actions instead of presses. The controls still work
+  /// for pausing and the menus.
+  Replay(Replay),
+}
+
+impl InputSource {
+  /// The source picked with `COINGIRL_REPLAY` or `COINGIRL_INPUT`, or the controls.
+  fn from_env() -> Self {
+    let loaded = if let Some(path) = std::env::var_os("COINGIRL_REPLAY") {
+      Replay::load(Path::new(&path)).map(InputSource::Replay)
+    } else if let Some(path) = std::env::var_os("COINGIRL_INPUT") {
+      InputScript::load(Path::new(&path)).map(InputSource::Script)
+    } else {
+      return InputSource::Hardware;
+    };
+    loaded.unwrap_or_else(|err| {
+      error!("{err}");
+      InputSource::Hardware
+    })
+  }
+}
+
+/// `button` held down for `frames` frames from `frame` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptedPress {
+  pub frame: u32,
+  pub player: Player,
+  pub button: Button,
+  pub frames: u32,
+}
+
+impl ScriptedPress {
+  fn covers(&self, frame: u32) -> bool {
+    (self.frame..self.frame + self.frames).contains(&frame)
+  }
+}
+
+/// Presses at set frames, counted by `GameInput::frame`.
+///
+/// ```ignore
+/// // start a solo game, then pull from the first column
+/// let script = InputScript::new()
+///   .tap(2, Player::P1, Button::A1)
+///   .tap(10, Player::P1, Button::StickLeft)
+///   .tap(12, Player::P1, Button::A1);
+/// app.insert_resource(InputSource::Script(script));
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputScript {
+  pub presses: Vec<ScriptedPress>,
+}
+
+impl InputScript {
+  pub fn new() -> Self {
+    InputScript::default()
+  }
+
+  /// Presses `button` on `frame` and lets go on the next one.
+  pub fn tap(self, frame: u32, player: Player, button: Button) -> Self {
+    self.hold(frame, player, button, 1)
+  }
+
+  pub fn hold(mut self, frame: u32, player: Player, button: Button, frames: u32) -> Self {
+    self.presses.push(ScriptedPress {
+      frame,
+      player,
+      button,
+      frames,
+    });
+    self
+  }
+
+  pub fn pressed(&self, frame: u32, player: Player, button: Button) -> bool {
+    self
+      .presses
+      .iter()
+      .any(|press| press.player == player && press.button == button && press.covers(frame))
+  }
+
+  /// The first frame after the last press is let go.
+  pub fn end(&self) -> u32 {
+    self
+      .presses
+      .iter()
+      .map(|press| press.frame + press.frames)
+      .max()
+      .unwrap_or(0)
+  }
+
+  pub fn load(path: &Path) -> Result<Self, String> {
+    ron_file::load(path, "input script")
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), String> {
+    ron_file::save(self, path)
+  }
+}
+
+/// Writes every hardware press into an `InputScript`, which is saved to `path` on exit.
+#[derive(Resource)]
+struct InputRecorder {
+  path: PathBuf,
+  script: InputScript,
+  /// The frame each held button went down on.
+  held: HashMap<(Player, Button), u32>,
+}
+
+pub struct InputPlugin;
+impl Plugin for InputPlugin {
+  fn build(&self, app: &mut App) {
+    if let Some(path) = std::env::var_os("COINGIRL_RECORD_INPUT") {
+      app.insert_resource(InputRecorder {
+        path: PathBuf::from(path),
+        script: InputScript::new(),
+        held: HashMap::new(),
+      });
+    }
+    app
+      .init_resource::<GameInput>()
+      .insert_resource(InputSource::from_env())
+      .add_systems(
+        (
+          read_hardware.run_if(reading_hardware),
+          read_script.run_if(not(reading_hardware)),
+          record_input
+            .after(read_hardware)
+            .run_if(reading_hardware)
+            .run_if(resource_exists::<InputRecorder>()),
+        )
+          .in_base_set(CoreSet::PreUpdate)
+          .after(InputSystem),
+      )
+      .add_systems((
+        close_on_menu_buttons,
+        save_input_recording
+          .in_base_set(CoreSet::Last)
+          .run_if(resource_exists::<InputRecorder>()),
+      ));
+  }
+}
+
+fn reading_hardware(source: Res<InputSource>) -> bool {
+  matches!(*source, InputSource::Hardware | InputSource::Replay(_))
+}
+
+fn read_script(mut input: ResMut<GameInput>, source: Res<InputSource>) {
+  let InputSource::Script(script) = &*source else {
+    return;
+  };
+  let frame = input.frame + 1;
+  input.update(|player, button| script.pressed(frame, player, button));
+}
+
+fn record_input(input: Res<GameInput>, mut recorder: ResMut<InputRecorder>) {
+  let frame = input.frame;
+  let recorder = &mut *recorder;
+  for player in Player::ALL {
+    for button in Button::ALL {
+      if input.just_pressed(player, button) {
+        recorder.held.insert((player, button), frame);
+      } else if input.just_released(player, button) {
+        let start = recorder.held.remove(&(player, button)).unwrap_or(frame);
+        recorder.script.presses.push(ScriptedPress {
+          frame: start,
+          player,
+          button,
+          frames: frame - start,
+        });
+      }
+    }
+  }
+}
+
+fn save_input_recording(mut exit: EventReader<AppExit>, recorder: Res<InputRecorder>) {
+  if exit.iter().next().is_none() {
+    return;
+  }
+  match recorder.script.save(&recorder.path) {
+    Ok(()) => info!("Input saved to {}", recorder.path.display()),
+    Err(err) => error!("{err}"),
+  }
+}
+
+/// On the cabinet, devcaders knows how its controls are wired up.
+#[cfg(feature = "devcade")]
+fn read_hardware(mut input: ResMut<GameInput>, cabinet: devcaders::DevcadeControls) {
+  input.update(|player, button| {
+    let player = match player {
+      Player::P1 => devcaders::Player::P1,
+      Player::P2 => devcaders::Player::P2,
+    };
+    let button = match button {
+      Button::StickLeft => devcaders::Button::StickLeft,
+      Button::StickRight => devcaders::Button::StickRight,
+      Button::StickUp => devcaders::Button::StickUp,
+      Button::StickDown => devcaders::Button::StickDown,
+      Button::A1 => devcaders::Button::A1,
+      Button::A2 => devcaders::Button::A2,
+      Button::A3 => devcaders::Button::A3,
+      Button::A4 => devcaders::Button::A4,
+      Button::Menu => devcaders::Button::Menu,
+    };
+    cabinet.pressed(player, button)
+  });
+}
+
+/// Off the cabinet, each player gets half of the keyboard and the first and second gamepads
+/// plugged in, whichever they like.
+#[cfg(not(feature = "devcade"))]
+fn read_hardware(
+  mut input: ResMut<GameInput>,
+  keyboard: Res<Input<KeyCode>>,
+  gamepads: Res<Gamepads>,
+  gamepad_buttons: Res<Input<GamepadButton>>,
+  gamepad_axes: Res<Axis<GamepadAxis>>,
+) {
+  input.update(|player, button| {
+    if keyboard.pressed(key_for(player, button)) {
+      return true;
+    }
+    let Some(gamepad) = gamepads.iter().nth(player.index()) else {
+      return false;
+    };
+    let (button_type, axis) = gamepad_binding(button);
+    let axis_pressed = axis.is_some_and(|(axis_type, sign)| {
+      let value = gamepad_axes
+        .get(GamepadAxis::new(gamepad, axis_type))
+        .unwrap_or(0.0);
+      value * sign > STICK_DEAD_ZONE
+    });
+    axis_pressed || gamepad_buttons.pressed(GamepadButton::new(gamepad, button_type))
+  });
+}
+
+/// How far a gamepad stick has to lean before it counts as pressed.
+#[cfg(not(feature = "devcade"))]
+const STICK_DEAD_ZONE: f32 = 0.5;
+
+/// P1 plays on the left of the keyboard and P2 on the right:
+///
+/// | Button   | P1     | P2        |
+/// |----------|--------|-----------|
+/// | Stick    | WASD   | Arrows    |
+/// | A1, A2   | F, G   | `,` `.`   |
+/// | A3, A4   | R, T   | K, L      |
+/// | Menu     | Escape | Backspace |
+#[cfg(not(feature = "devcade"))]
+fn key_for(player: Player, button: Button) -> KeyCode {
+  match (player, button) {
+    (Player::P1, Button::StickLeft) => KeyCode::A,
+    (Player::P1, Button::StickRight) => KeyCode::D,
+    (Player::P1, Button::StickUp) => KeyCode::W,
+    (Player::P1, Button::StickDown) => KeyCode::S,
+    (Player::P1, Button::A1) => KeyCode::F,
+    (Player::P1, Button::A2) => KeyCode::G,
+    (Player::P1, Button::A3) => KeyCode::R,
+    (Player::P1, Button::A4) => KeyCode::T,
+    (Player::P1, Button::Menu) => KeyCode::Escape,
+
+    (Player::P2, Button::StickLeft) => KeyCode::Left,
+    (Player::P2, Button::StickRight) => KeyCode::Right,
+    (Player::P2, Button::StickUp) => KeyCode::Up,
+    (Player::P2, Button::StickDown) => KeyCode::Down,
+    (Player::P2, Button::A1) => KeyCode::Comma,
+    (Player::P2, Button::A2) => KeyCode::Period,
+    (Player::P2, Button::A3) => KeyCode::K,
+    (Player::P2, Button::A4) => KeyCode::L,
+    (Player::P2, Button::Menu) => KeyCode::Back,
+  }
+}
+
+/// The gamepad button for `button`, and the left stick direction that also presses it.
+#[cfg(not(feature = "devcade"))]
+fn gamepad_binding(button: Button) -> (GamepadButtonType, Option<(GamepadAxisType, f32)>) {
+  match button {
+    Button::StickLeft => (
+      GamepadButtonType::DPadLeft,
+      Some((GamepadAxisType::LeftStickX, -1.0)),
+    ),
+    Button::StickRight => (
+      GamepadButtonType::DPadRight,
+      Some((GamepadAxisType::LeftStickX, 1.0)),
+    ),
+    Button::StickUp => (
+      GamepadButtonType::DPadUp,
+      Some((GamepadAxisType::LeftStickY, 1.0)),
+    ),
+    Button::StickDown => (
+      GamepadButtonType::DPadDown,
+      Some((GamepadAxisType::LeftStickY, -1.0)),
+    ),
+    Button::A1 => (GamepadButtonType::South, None),
+    Button::A2 => (GamepadButtonType::East, None),
+    Button::A3 => (GamepadButtonType::West, None),
+    Button::A4 => (GamepadButtonType::North, None),
+    Button::Menu => (GamepadButtonType::Start, None),
+  }
+}
+
+/// Closes the focused window when both menu buttons are held.
+fn close_on_menu_buttons(
+  mut commands: Commands,
+  windows: Query<(Entity, &Window)>,
+  input: Res<GameInput>,
+) {
+  if !(input.pressed(Player::P1, Button::Menu) && input.pressed(Player::P2, Button::Menu)) {
+    return;
+  }
+  for (entity, window) in &windows {
+    if window.focused {
+      commands.entity(entity).despawn();
+    }
+  }
+}
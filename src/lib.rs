@@ -0,0 +1,7 @@
+//! The rules of the game, with nothing that needs a window. Shared by the game and
+//! `coingirl-sim`.
+pub mod board;
+pub mod bot;
+pub mod coins;
+pub mod curve;
+pub mod scoring;
@@ -1,37 +1,92 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 #![allow(unused_imports)]
+#![allow(clippy::type_complexity)]
+#![allow(clippy::too_many_arguments)]
 mod consts;
 use crate::consts::*;
 use bevy::window::WindowResolution;
 use bevy::{prelude::*, window::WindowResized};
-use devcaders::{Button, DevcadeControls, Player};
 
 mod menu;
+use coingirlgame_devcaders::{board, bot, coins, curve, scoring};
+mod coin_set;
+use coin_set::CoinsPlugin;
 use menu::MenuPlugin;
+mod menu_ui;
+use menu_ui::MenuUiPlugin;
+mod cpu;
+use cpu::{CpuPlugin, Difficulty};
+mod danger;
+use danger::DangerPlugin;
+mod effects;
+use effects::EffectsPlugin;
 mod game;
+mod replay;
+use replay::ReplayPlugin;
+mod highscore;
+mod hint;
+mod input;
+use highscore::HighScorePlugin;
+use hint::HintPlugin;
+use input::{InputPlugin, Player};
+mod pause;
+use pause::PausePlugin;
+mod preview;
+use preview::PreviewPlugin;
+mod rng;
+mod ron_file;
+mod rules;
+use rules::Rules;
+mod settings;
+use settings::SettingsPlugin;
+mod score;
 use game::{get_board_quad, get_board_transform, GamePlugin};
 use rand::rngs::ThreadRng;
+use score::ScorePlugin;
+mod sound;
+use sound::SoundPlugin;
+mod speed;
+use speed::SpeedPlugin;
+mod tween;
+use tween::TweenPlugin;
+mod versus;
+use std::path::PathBuf;
+use versus::VersusPlugin;
 
 #[derive(Resource, Default)]
 pub struct GameInfo {
   players: usize,
+  /// P2 is played by the computer.
+  cpu: bool,
+  difficulty: Difficulty,
+  /// Played for practice: hints are allowed and the scores aren't ranked.
+  practice: bool,
+  /// Seed for the next game's `GameRng`. A random one is picked when this is `None`.
+  seed: Option<u64>,
+}
+impl GameInfo {
+  /// How the mode is shown in the high score table.
+  pub fn mode_name(&self) -> &'static str {
+    match (self.players, self.cpu) {
+      (1, _) => "Solo",
+      (_, true) => "Vs CPU",
+      _ => "Versus",
+    }
+  }
 }
 
 #[derive(Resource, Default)]
 pub struct RenderInfo {
   coin_size: f32,
+  /// The on-screen size of a board's background.
+  board_size: Vec2,
+  board_width: i32,
+  board_height: i32,
   transform_p1: Transform,
   transform_p2: Transform,
 }
 impl RenderInfo {
-  pub fn default() -> Self {
-    RenderInfo {
-      coin_size: 0.0,
-      transform_p1: Transform::from_translation(Vec3::ZERO),
-      transform_p2: Transform::from_translation(Vec3::ZERO),
-    }
-  }
   pub fn board_transform(&self, player: Player) -> Transform {
     match player {
       Player::P1 => self.transform_p1,
@@ -39,54 +94,74 @@ impl RenderInfo {
     }
   }
 
+  /// The center of the cell at `col`, `row`. Row 0 is the top of the board.
   pub fn obj_translate(&self, player: Player, col: i32, row: i32) -> Vec2 {
+    let center_col = (self.board_width - 1) as f32 / 2.0;
+    let center_row = (self.board_height - 1) as f32 / 2.0;
+    Vec2::new(
+      self.board_transform(player).translation.x + (col as f32 - center_col) * self.coin_size,
+      self.board_transform(player).translation.y + (center_row - row as f32) * self.coin_size,
+    )
+  }
+
+  /// The center of the next row's preview coin for `col`, in the strip just above the board.
+  pub fn preview_translate(&self, player: Player, col: i32) -> Vec2 {
     Vec2::new(
-      self.board_transform(player).translation.x + (-3.0 + col as f32) * self.coin_size,
-      self.board_transform(player).translation.y + (5.5 - row as f32) * self.coin_size,
+      self.obj_translate(player, col, 0).x,
+      self.board_transform(player).translation.y
+        + (self.board_size.y + PREVIEW_SCALE * self.coin_size) / 2.0,
     )
   }
+
+  /// The top left corner of the HUD readout `row` coins down, just right of the board.
+  pub fn hud_translate(&self, player: Player, row: i32) -> Vec2 {
+    self.obj_translate(player, self.board_width, row) + Vec2::new(-0.5, 0.5) * self.coin_size
+  }
+
+  pub fn hud_font_size(&self) -> f32 {
+    self.coin_size * HUD_FONT_SCALE
+  }
+
+  fn update(&mut self, players: usize, rules: &Rules, resolution: &WindowResolution) {
+    let board_quad = get_board_quad(players, Player::P1, rules, resolution);
+    self.board_size = board_quad.size;
+    self.board_width = rules.board_width;
+    self.board_height = rules.board_height;
+    self.transform_p1 = get_board_transform(players, Player::P1, resolution);
+    self.transform_p2 = get_board_transform(players, Player::P2, resolution);
+    self.coin_size = (board_quad.size.x - 2.0 * BOARD_MARGIN.evaluate(board_quad.size.x).unwrap())
+      / rules.board_width as f32;
+  }
+}
+
+/// Where saved files such as replays go, unless `COINGIRL_DATA_DIR` points somewhere else.
+pub fn data_dir() -> PathBuf {
+  if let Some(dir) = std::env::var_os("COINGIRL_DATA_DIR") {
+    return PathBuf::from(dir);
+  }
+  dirs::data_dir()
+    .unwrap_or_else(|| PathBuf::from("."))
+    .join("coingirlgame")
 }
 
 fn init_render_info(
   mut render_info: ResMut<RenderInfo>,
   game_info: Res<GameInfo>,
+  rules: Res<Rules>,
   window: Query<&mut Window>,
 ) {
-  let board_quad = get_board_quad(game_info.players, Player::P1, &window.single().resolution);
-  render_info.transform_p1 =
-    get_board_transform(game_info.players, Player::P1, &window.single().resolution);
-  render_info.transform_p2 =
-    get_board_transform(game_info.players, Player::P2, &window.single().resolution);
-  render_info.coin_size = (board_quad.size.x
-    - 2.0 * BOARD_MARGIN.evaluate(board_quad.size.x).unwrap())
-    / BOARD_DIM.0 as f32;
+  render_info.update(game_info.players, &rules, &window.single().resolution);
 }
 
 fn update_render_info(
   mut event: EventReader<WindowResized>,
   mut render_info: ResMut<RenderInfo>,
   game_info: Res<GameInfo>,
+  rules: Res<Rules>,
   window: Query<&mut Window>,
 ) {
-  if game_info.is_changed() {
-    let board_quad = get_board_quad(game_info.players, Player::P1, &window.single().resolution);
-    render_info.transform_p1 =
-      get_board_transform(game_info.players, Player::P1, &window.single().resolution);
-    render_info.transform_p2 =
-      get_board_transform(game_info.players, Player::P2, &window.single().resolution);
-    render_info.coin_size = (board_quad.size.x
-      - 2.0 * BOARD_MARGIN.evaluate(board_quad.size.x).unwrap())
-      / BOARD_DIM.0 as f32;
-  }
-  for ev in event.iter() {
-    let board_quad = get_board_quad(game_info.players, Player::P1, &window.single().resolution);
-    render_info.transform_p1 =
-      get_board_transform(game_info.players, Player::P1, &window.single().resolution);
-    render_info.transform_p2 =
-      get_board_transform(game_info.players, Player::P2, &window.single().resolution);
-    render_info.coin_size = (board_quad.size.x
-      - 2.0 * BOARD_MARGIN.evaluate(board_quad.size.x).unwrap())
-      / BOARD_DIM.0 as f32;
+  if game_info.is_changed() || rules.is_changed() || event.iter().count() > 0 {
+    render_info.update(game_info.players, &rules, &window.single().resolution);
   }
 }
 
@@ -158,7 +233,8 @@ fn setup_game(mut commands: Commands, asset_server: Res<AssetServer>) {
 }
 
 fn main() {
-  App::new()
+  let mut app = App::new();
+  app
     .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
     .add_plugins(
       DefaultPlugins
@@ -177,15 +253,189 @@ fn main() {
           ..default()
         })
         .set(ImagePlugin::default_nearest()),
-    )
+    );
+  add_game(&mut app).add_plugin(SoundPlugin).run();
+}
+
+/// The game's states, resources and plugins, apart from sound. Tests run it headless, with
+/// bevy's minimal plugins and a window that's never opened.
+fn add_game(app: &mut App) -> &mut App {
+  app
     .add_state::<AppState>()
+    .add_state::<PauseState>()
     .add_startup_system(setup)
-    .insert_resource(GameInfo { players: 2 })
+    .insert_resource(GameInfo {
+      players: 2,
+      cpu: false,
+      difficulty: Difficulty::default(),
+      practice: false,
+      seed: std::env::var("COINGIRL_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok()),
+    })
+    .insert_resource(Rules::from_env())
     .insert_resource(RenderInfo::default())
     .add_startup_system(init_render_info)
+    .add_plugin(CoinsPlugin)
     .add_plugin(MenuPlugin)
+    .add_plugin(MenuUiPlugin)
     .add_plugin(GamePlugin)
-    .add_systems((devcaders::close_on_menu_buttons, update_render_info))
-    // .insert_resource(FixedTime::new_from_secs(1.0 / 30.0))
-    .run();
+    .add_plugin(CpuPlugin)
+    .add_plugin(DangerPlugin)
+    .add_plugin(EffectsPlugin)
+    .add_plugin(HighScorePlugin)
+    .add_plugin(HintPlugin)
+    .add_plugin(InputPlugin)
+    .add_plugin(PausePlugin)
+    .add_plugin(PreviewPlugin)
+    .add_plugin(ReplayPlugin)
+    .add_plugin(ScorePlugin)
+    .add_plugin(SettingsPlugin)
+    .add_plugin(SpeedPlugin)
+    .add_plugin(TweenPlugin)
+    .add_plugin(VersusPlugin)
+    .add_system(update_render_info)
+  // .insert_resource(FixedTime::new_from_secs(1.0 / 30.0))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use bevy::sprite::ColorMaterial;
+  use bevy::time::TimeUpdateStrategy;
+
+  use super::*;
+  use crate::input::{Button, GameInput, InputScript, InputSource};
+
+  /// How much game time each `step` covers.
+  const FRAME: Duration = Duration::from_millis(250);
+
+  /// The game without a window or sound.
+  fn headless_app() -> App {
+    // keeps the replays and high scores these games make out of the real ones
+    let data_dir = std::env::temp_dir().join(format!("coingirl-test-{}", std::process::id()));
+    std::env::set_var("COINGIRL_DATA_DIR", &data_dir);
+    let mut app = App::new();
+    app
+      .add_plugins(MinimalPlugins)
+      .add_plugin(AssetPlugin::default())
+      .add_plugin(bevy::input::InputPlugin)
+      .add_plugin(WindowPlugin::default())
+      .add_asset::<Image>()
+      .add_asset::<Font>()
+      .add_asset::<Mesh>()
+      .add_asset::<ColorMaterial>();
+    add_game(&mut app);
+    app.world.resource_mut::<GameInfo>().seed = Some(7);
+    app
+  }
+
+  /// Runs a frame `FRAME` after the last one, however long it really took.
+  fn step(app: &mut App) {
+    let time = app.world.resource::<Time>();
+    let last = time.last_update().unwrap_or_else(|| time.startup());
+    app.insert_resource(TimeUpdateStrategy::ManualInstant(last + FRAME));
+    app.update();
+  }
+
+  fn state(app: &App) -> AppState {
+    app.world.resource::<State<AppState>>().0
+  }
+
+  /// Taps `buttons` one after another from the next frame on, then steps until the game gets
+  /// to `wanted`, giving up `max_frames` after the last tap is let go.
+  fn tap_until(app: &mut App, buttons: &[(Player, Button)], wanted: AppState, max_frames: u32) {
+    let start = app.world.resource::<GameInput>().frame() + 1;
+    let script = buttons
+      .iter()
+      .enumerate()
+      .fold(InputScript::new(), |script, (i, &(player, button))| {
+        script.tap(start + 4 * i as u32, player, button)
+      });
+    let deadline = script.end().max(start) + max_frames;
+    app.insert_resource(InputSource::Script(script));
+    while app.world.resource::<GameInput>().frame() < deadline {
+      step(app);
+      if state(app) == wanted {
+        return;
+      }
+    }
+    panic!("stuck in {:?} instead of {wanted:?}", state(app));
+  }
+
+  /// Wider than Evogria's widest letter, as a fraction of the font size.
+  const GLYPH_WIDTH: f32 = 0.7;
+  /// Taller than a line of Evogria, as a fraction of the font size.
+  const LINE_HEIGHT: f32 = 1.3;
+
+  #[test]
+  fn hud_fits_in_the_window() {
+    // the longest lines are the versus readout's title and a six figure score
+    let longest = ["Incoming", "Score", "999999"]
+      .map(str::len)
+      .into_iter()
+      .max()
+      .unwrap();
+    for resolution in [
+      WindowResolution::new(RESOLUTION_X, RESOLUTION_Y),
+      WindowResolution::new(1920.0, 1080.0),
+    ] {
+      let (half_width, half_height) = (resolution.width() / 2.0, resolution.height() / 2.0);
+      for players in [1, 2] {
+        let mut render_info = RenderInfo::default();
+        render_info.update(players, &Rules::default(), &resolution);
+        for player in &Player::ALL[..players] {
+          // the versus readout sits lowest, two rows under the score
+          let top_left = render_info.hud_translate(*player, 0);
+          let bottom_right = render_info.hud_translate(*player, 2)
+            + Vec2::new(longest as f32 * GLYPH_WIDTH, -2.0 * LINE_HEIGHT)
+              * render_info.hud_font_size();
+          let inside = |pos: Vec2| pos.x.abs() <= half_width && pos.y.abs() <= half_height;
+          assert!(
+            inside(top_left) && inside(bottom_right),
+            "{players} player HUD for {player:?} runs off a {}x{} window",
+            resolution.width(),
+            resolution.height()
+          );
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn replay_with_a_bad_board_is_refused() {
+    let path = std::env::temp_dir().join(format!("coingirl-bad-{}.replay.ron", std::process::id()));
+    let rules = Rules {
+      board_width: 0,
+      board_height: 12,
+    };
+    replay::Replay::new(7, 1, rules).save(&path).unwrap();
+    let loaded = replay::Replay::load(&path);
+    let _ = std::fs::remove_file(&path);
+    assert!(loaded.unwrap_err().contains("out of range"));
+  }
+
+  #[test]
+  fn menu_game_lost_menu() {
+    let mut app = headless_app();
+    step(&mut app);
+    assert_eq!(state(&app), AppState::Menu);
+
+    // Play, then 1 Player
+    let start = [(Player::P1, Button::A1), (Player::P1, Button::A1)];
+    tap_until(&mut app, &start, AppState::Game, 20);
+    assert_eq!(app.world.resource::<GameInfo>().players, 1);
+
+    // nobody plays, so the rows pile up
+    tap_until(&mut app, &[], AppState::Lost, 2000);
+
+    // nothing was scored, so there are no initials to enter
+    tap_until(&mut app, &[(Player::P1, Button::A4)], AppState::Menu, 20);
+    step(&mut app);
+    let mut girls = app.world.query_filtered::<(), With<game::CoinGirl>>();
+    assert_eq!(girls.iter(&app.world).count(), 0);
+    assert!(app.world.contains_resource::<menu_ui::MenuStack>());
+    let _ = std::fs::remove_dir_all(data_dir());
+  }
 }
@@ -0,0 +1,396 @@
+use bevy::prelude::*;
+
+use crate::input::{Button, GameInput, Player};
+
+const IDLE_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_COLOR: Color = Color::rgb(0.75, 0.75, 0.75);
+
+/// A list of entries, shown as a column of buttons under a title. Menus are plain data: entries
+/// read and change the world through the functions they're built with, so any screen can make
+/// one without its own input or layout systems.
+pub struct Menu {
+  title: String,
+  entries: Vec<MenuEntry>,
+  /// Run when the menu is backed out of, before it closes.
+  on_back: Option<fn(&mut World)>,
+  background: Option<Color>,
+  selected: usize,
+}
+
+struct MenuEntry {
+  label: String,
+  kind: EntryKind,
+}
+
+enum EntryKind {
+  Action(fn(&mut World)),
+  Submenu(fn(&World) -> Menu),
+  Toggle {
+    get: fn(&World) -> bool,
+    set: fn(&mut World, bool),
+  },
+  Slider {
+    max: u8,
+    get: fn(&World) -> u8,
+    set: fn(&mut World, u8),
+  },
+  Choice {
+    options: Vec<String>,
+    get: fn(&World) -> usize,
+    set: fn(&mut World, usize),
+  },
+  Back,
+}
+
+impl Menu {
+  pub fn new(title: impl Into<String>) -> Self {
+    Menu {
+      title: title.into(),
+      entries: Vec::new(),
+      on_back: None,
+      background: None,
+      selected: 0,
+    }
+  }
+
+  /// Runs `action` when picked.
+  pub fn action(self, label: impl Into<String>, action: fn(&mut World)) -> Self {
+    self.entry(label, EntryKind::Action(action))
+  }
+
+  /// Opens the menu `build` makes when picked. Backing out of it comes back here.
+  pub fn submenu(self, label: impl Into<String>, build: fn(&World) -> Menu) -> Self {
+    self.entry(label, EntryKind::Submenu(build))
+  }
+
+  /// On or off, flipped by picking it or by left and right.
+  pub fn toggle(
+    self,
+    label: impl Into<String>,
+    get: fn(&World) -> bool,
+    set: fn(&mut World, bool),
+  ) -> Self {
+    self.entry(label, EntryKind::Toggle { get, set })
+  }
+
+  /// A number from 0 to `max`, stepped by left and right.
+  pub fn slider(
+    self,
+    label: impl Into<String>,
+    max: u8,
+    get: fn(&World) -> u8,
+    set: fn(&mut World, u8),
+  ) -> Self {
+    self.entry(label, EntryKind::Slider { max, get, set })
+  }
+
+  /// One of `options`, cycled through by left and right. `get` and `set` take its index.
+  pub fn choice(
+    self,
+    label: impl Into<String>,
+    options: impl IntoIterator<Item = impl Into<String>>,
+    get: fn(&World) -> usize,
+    set: fn(&mut World, usize),
+  ) -> Self {
+    let options = options.into_iter().map(Into::into).collect();
+    self.entry(label, EntryKind::Choice { options, get, set })
+  }
+
+  /// Backs out of the menu, the same as the back button.
+  pub fn back(self, label: impl Into<String>) -> Self {
+    self.entry(label, EntryKind::Back)
+  }
+
+  pub fn on_back(mut self, on_back: fn(&mut World)) -> Self {
+    self.on_back = Some(on_back);
+    self
+  }
+
+  /// Shades everything behind the menu, for one shown over the game.
+  pub fn with_background(mut self, color: Color) -> Self {
+    self.background = Some(color);
+    self
+  }
+
+  fn entry(mut self, label: impl Into<String>, kind: EntryKind) -> Self {
+    self.entries.push(MenuEntry {
+      label: label.into(),
+      kind,
+    });
+    self
+  }
+}
+
+impl MenuEntry {
+  fn text(&self, world: &World) -> String {
+    match &self.kind {
+      EntryKind::Toggle { get, .. } => {
+        format!("{}: {}", self.label, if get(world) { "On" } else { "Off" })
+      }
+      EntryKind::Slider { get, .. } => format!("< {}: {} >", self.label, get(world)),
+      EntryKind::Choice { options, get, .. } => {
+        let option = options.get(get(world)).map_or("", String::as_str);
+        format!("< {}: {} >", self.label, option)
+      }
+      _ => self.label.clone(),
+    }
+  }
+}
+
+/// The open menus, innermost last. Inserting one puts its menu on screen, and removing it takes
+/// it down again.
+#[derive(Resource)]
+pub struct MenuStack {
+  menus: Vec<Menu>,
+  /// The UI showing the innermost menu, once it's been built.
+  root: Option<Entity>,
+}
+
+impl MenuStack {
+  pub fn new(menu: Menu) -> Self {
+    MenuStack {
+      menus: vec![menu],
+      root: None,
+    }
+  }
+
+  fn top(&mut self) -> &mut Menu {
+    self
+      .menus
+      .last_mut()
+      .expect("the menu stack is never empty")
+  }
+
+  fn open(&mut self, menu: Menu) {
+    self.menus.push(menu);
+    self.root = None;
+  }
+}
+
+/// The UI of the menu on screen.
+#[derive(Component)]
+struct MenuRoot;
+
+/// The button for the entry at this index in the menu on screen.
+#[derive(Component)]
+struct EntryButton(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+  Up,
+  Down,
+  Left,
+  Right,
+  Pick,
+  Back,
+}
+
+pub struct MenuUiPlugin;
+impl Plugin for MenuUiPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems((
+      menu_input.run_if(resource_exists::<MenuStack>()),
+      show_menu
+        .after(menu_input)
+        .run_if(resource_exists::<MenuStack>()),
+      cleanup_menu.run_if(not(resource_exists::<MenuStack>())),
+    ));
+  }
+}
+
+/// Either player can drive the menu, one command a frame.
+fn read_command(input: &GameInput) -> Option<Command> {
+  const BUTTONS: [(Button, Command); 7] = [
+    (Button::StickUp, Command::Up),
+    (Button::StickDown, Command::Down),
+    (Button::StickLeft, Command::Left),
+    (Button::StickRight, Command::Right),
+    (Button::A1, Command::Pick),
+    (Button::A4, Command::Back),
+    (Button::Menu, Command::Back),
+  ];
+  Player::ALL.into_iter().find_map(|player| {
+    BUTTONS
+      .into_iter()
+      .find(|&(button, _)| input.just_released(player, button))
+      .map(|(_, command)| command)
+  })
+}
+
+fn menu_input(world: &mut World) {
+  let Some(command) = read_command(world.resource::<GameInput>()) else {
+    return;
+  };
+  // actions get the whole world, menu stack included, so they run once it's been put back
+  let action = world.resource_scope(|world, mut stack: Mut<MenuStack>| {
+    let menu = stack.top();
+    let count = menu.entries.len();
+    if count == 0 && command != Command::Back {
+      return None;
+    }
+    match command {
+      Command::Up => {
+        menu.selected = (menu.selected + count - 1) % count;
+        None
+      }
+      Command::Down => {
+        menu.selected = (menu.selected + 1) % count;
+        None
+      }
+      Command::Left | Command::Right => {
+        let step = if command == Command::Left { -1 } else { 1 };
+        let entry = &menu.entries[menu.selected];
+        match &entry.kind {
+          EntryKind::Toggle { get, set } => {
+            let on = !get(world);
+            set(world, on);
+          }
+          &EntryKind::Slider { max, get, set } => {
+            let value = (get(world) as i32 + step).clamp(0, max as i32);
+            set(world, value as u8);
+          }
+          EntryKind::Choice { options, get, set } if !options.is_empty() => {
+            let len = options.len() as i32;
+            let index = (get(world) as i32 + step).rem_euclid(len);
+            set(world, index as usize);
+          }
+          _ => (),
+        }
+        None
+      }
+      Command::Pick => match &menu.entries[menu.selected].kind {
+        &EntryKind::Action(action) => Some(action),
+        &EntryKind::Submenu(build) => {
+          let submenu = build(world);
+          stack.open(submenu);
+          None
+        }
+        EntryKind::Toggle { get, set } => {
+          let on = !get(world);
+          set(world, on);
+          None
+        }
+        EntryKind::Back => back(&mut stack),
+        EntryKind::Slider { .. } | EntryKind::Choice { .. } => None,
+      },
+      Command::Back => back(&mut stack),
+    }
+  });
+  if let Some(action) = action {
+    action(world);
+  }
+}
+
+/// Closes a submenu, or leaves the outermost menu to its `on_back`.
+fn back(stack: &mut MenuStack) -> Option<fn(&mut World)> {
+  if stack.menus.len() > 1 {
+    let menu = stack.menus.pop().unwrap();
+    stack.root = None;
+    menu.on_back
+  } else {
+    stack.top().on_back
+  }
+}
+
+/// Builds the UI for the innermost menu when it changes, and keeps its labels and highlight up
+/// to date.
+fn show_menu(world: &mut World) {
+  let (selected, texts) = world.resource_scope(|world, mut stack: Mut<MenuStack>| {
+    if stack.root.is_none() {
+      let mut old_roots = world.query_filtered::<Entity, With<MenuRoot>>();
+      for entity in old_roots.iter(world).collect::<Vec<_>>() {
+        world.entity_mut(entity).despawn_recursive();
+      }
+      stack.root = Some(spawn_menu(world, stack.top()));
+    }
+    let menu = stack.top();
+    let texts: Vec<String> = menu.entries.iter().map(|entry| entry.text(world)).collect();
+    (menu.selected, texts)
+  });
+
+  let mut buttons = world.query::<(&EntryButton, &mut BackgroundColor, &Children)>();
+  let mut labels = Vec::new();
+  for (button, mut color, children) in buttons.iter_mut(world) {
+    let wanted = if button.0 == selected {
+      SELECTED_COLOR
+    } else {
+      IDLE_COLOR
+    };
+    if color.0 != wanted {
+      *color = wanted.into();
+    }
+    labels.extend(children.iter().map(|&child| (child, button.0)));
+  }
+  for (child, index) in labels {
+    if let Some(mut text) = world.get_mut::<Text>(child) {
+      // only touched when it differs, so the text isn't laid out again every frame
+      if text.sections[0].value != texts[index] {
+        text.sections[0].value = texts[index].clone();
+      }
+    }
+  }
+}
+
+fn spawn_menu(world: &mut World, menu: &Menu) -> Entity {
+  let font: Handle<Font> = world.resource::<AssetServer>().load("Evogria.otf");
+  let texts: Vec<String> = menu.entries.iter().map(|entry| entry.text(world)).collect();
+  let mut root = world.spawn((
+    NodeBundle {
+      style: Style {
+        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        flex_direction: FlexDirection::Column,
+        gap: Size::height(Val::Px(20.0)),
+        ..default()
+      },
+      background_color: menu.background.unwrap_or(Color::NONE).into(),
+      z_index: ZIndex::Global(1),
+      ..default()
+    },
+    MenuRoot,
+  ));
+  root.with_children(|parent| {
+    parent.spawn(TextBundle::from_section(
+      menu.title.clone(),
+      TextStyle {
+        font: font.clone(),
+        font_size: 80.0,
+        color: Color::WHITE,
+      },
+    ));
+    for (i, text) in texts.into_iter().enumerate() {
+      parent
+        .spawn((
+          ButtonBundle {
+            style: Style {
+              size: Size::new(Val::Px(400.0), Val::Px(65.0)),
+              justify_content: JustifyContent::Center,
+              align_items: AlignItems::Center,
+              ..default()
+            },
+            background_color: IDLE_COLOR.into(),
+            ..default()
+          },
+          EntryButton(i),
+        ))
+        .with_children(|parent| {
+          parent.spawn(TextBundle::from_section(
+            text,
+            TextStyle {
+              font: font.clone(),
+              font_size: 40.0,
+              color: Color::WHITE,
+            },
+          ));
+        });
+    }
+  });
+  root.id()
+}
+
+fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+  for entity in &query {
+    commands.entity(entity).despawn_recursive();
+  }
+}
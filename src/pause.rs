@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+use crate::consts::{AppState, PauseState};
+use crate::input::{Button, GameInput, Player};
+use crate::menu_ui::{Menu, MenuStack};
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+  fn build(&self, app: &mut App) {
+    app
+      // freezes every game system, including their timers
+      .configure_set(OnUpdate(AppState::Game).run_if(in_state(PauseState::Running)))
+      .add_systems((
+        pause_input
+          .run_if(in_state(AppState::Game))
+          .run_if(in_state(PauseState::Running)),
+        setup_pause_menu.in_schedule(OnEnter(PauseState::Paused)),
+      ));
+  }
+}
+
+fn pause_input(input: Res<GameInput>, mut next_pause_state: ResMut<NextState<PauseState>>) {
+  if input.just_released(Player::P1, Button::Menu) || input.just_released(Player::P2, Button::Menu)
+  {
+    next_pause_state.set(PauseState::Paused);
+  }
+}
+
+fn pause_menu() -> Menu {
+  Menu::new("Paused")
+    .action("Resume", |world| resume(world, None))
+    // re-entering the state tears the game down and sets it up again
+    .action("Restart", |world| resume(world, Some(AppState::Game)))
+    .action("Quit to Menu", |world| resume(world, Some(AppState::Menu)))
+    .on_back(|world| resume(world, None))
+    .with_background(Color::rgba(0.0, 0.0, 0.0, 0.7))
+}
+
+fn setup_pause_menu(mut commands: Commands) {
+  commands.insert_resource(MenuStack::new(pause_menu()));
+}
+
+/// Takes the pause menu down straight away, rather than when leaving `PauseState::Paused`, so it
+/// can't take the main menu down with it on the way out of the game.
+fn resume(world: &mut World, next_state: Option<AppState>) {
+  world.remove_resource::<MenuStack>();
+  world
+    .resource_mut::<NextState<PauseState>>()
+    .set(PauseState::Running);
+  if let Some(next_state) = next_state {
+    world.resource_mut::<NextState<AppState>>().set(next_state);
+  }
+}
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use crate::consts::*;
+use crate::game::{Board, CoinGirl, NextRow};
+use crate::input::Player;
+use crate::rules::Rules;
+use crate::RenderInfo;
+
+/// How see-through the preview is, so it doesn't pass for coins already on the board.
+const PREVIEW_ALPHA: f32 = 0.45;
+
+/// Shows the coin of the next row that lands in `col`.
+#[derive(Component)]
+struct PreviewCoin {
+  col: i32,
+}
+
+pub struct PreviewPlugin;
+impl Plugin for PreviewPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems((
+      setup_preview.in_schedule(OnEnter(AppState::Game)),
+      update_preview.in_set(OnUpdate(AppState::Game)),
+      cleanup_preview.in_schedule(OnExit(AppState::Game)),
+    ));
+  }
+}
+
+fn setup_preview(mut commands: Commands, rules: Res<Rules>) {
+  for player in Player::ALL {
+    for col in 0..rules.board_width {
+      commands.spawn((
+        SpriteBundle {
+          sprite: Sprite {
+            color: Color::rgba(1.0, 1.0, 1.0, PREVIEW_ALPHA),
+            ..default()
+          },
+          visibility: Visibility::Hidden,
+          ..default()
+        },
+        PreviewCoin { col },
+        player,
+      ));
+    }
+  }
+}
+
+/// Dims the next row into the strip above each board. Only redone when a row is dealt.
+fn update_preview(
+  girl_query: Query<(&Player, &Board, Ref<NextRow>), With<CoinGirl>>,
+  mut preview_query: Query<(
+    &PreviewCoin,
+    &Player,
+    &mut Handle<Image>,
+    &mut Transform,
+    &mut Visibility,
+  )>,
+  asset_server: Res<AssetServer>,
+  render_info: Res<RenderInfo>,
+) {
+  for (&player, board, next_row) in &girl_query {
+    if !next_row.is_changed() && !render_info.is_changed() {
+      continue;
+    }
+    for (coin, &coin_player, mut texture, mut transform, mut visibility) in &mut preview_query {
+      if coin_player != player {
+        continue;
+      }
+      let Some(&obj_type) = next_row.get(coin.col as usize) else {
+        *visibility = Visibility::Hidden;
+        continue;
+      };
+      *visibility = Visibility::Visible;
+      *texture = asset_server.load(board.coin_set().sprite(obj_type));
+      *transform = Transform {
+        translation: render_info
+          .preview_translate(player, coin.col)
+          .extend(COIN_Z),
+        scale: Vec3::splat(PREVIEW_SCALE * render_info.coin_size / COIN_SIZE_PX),
+        ..default()
+      };
+    }
+  }
+}
+
+fn cleanup_preview(mut commands: Commands, query: Query<Entity, With<PreviewCoin>>) {
+  for entity in &query {
+    commands.entity(entity).despawn_recursive();
+  }
+}
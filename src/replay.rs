@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::consts::AppState;
+use crate::game::{ActionType, SendActions};
+use crate::input::{InputSource, Player};
+use crate::rules::Rules;
+use crate::{ron_file, GameInfo};
+
+/// A single `GameActionEvent`, stamped with the game tick it happened on.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReplayAction {
+  pub tick: u32,
+  pub player: Player,
+  pub action_type: ActionType,
+}
+
+/// Everything needed to play a game back exactly: the `GameRng` seed, the `GameInfo` and `Rules`
+/// it was started with, and every action in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+  pub seed: u64,
+  pub players: usize,
+  #[serde(default)]
+  pub rules: Rules,
+  pub actions: Vec<ReplayAction>,
+}
+
+impl Replay {
+  pub fn new(seed: u64, players: usize, rules: Rules) -> Self {
+    Replay {
+      seed,
+      players,
+      rules,
+      actions: Vec::new(),
+    }
+  }
+
+  /// Reads a replay, refusing one whose rules or player count no game could start with.
+  pub fn load(path: &Path) -> Result<Self, String> {
+    let replay: Replay = ron_file::load(path, "replay")?;
+    let invalid = |err: String| format!("invalid replay {}: {err}", path.display());
+    replay.rules.validate().map_err(invalid)?;
+    if !(1..=2).contains(&replay.players) {
+      return Err(invalid(format!("{} players", replay.players)));
+    }
+    Ok(replay)
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), String> {
+    ron_file::save(self, path)
+  }
+
+  /// Where a game recorded now is saved. Every game gets its own file, named for when it was
+  /// saved and its seed.
+  pub fn new_path(&self) -> PathBuf {
+    let secs = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |since| since.as_secs());
+    crate::data_dir()
+      .join("replays")
+      .join(format!("{secs}-{}.replay.ron", self.seed))
+  }
+}
+
+/// Whether the current game is being recorded or played back from a file.
+#[derive(Resource)]
+pub enum ReplayMode {
+  Record(Replay),
+  Playback { replay: Replay, cursor: usize },
+}
+
+impl ReplayMode {
+  pub fn is_playback(&self) -> bool {
+    matches!(self, ReplayMode::Playback { .. })
+  }
+
+  /// Every recorded action has been played back.
+  pub fn is_finished(&self) -> bool {
+    match self {
+      ReplayMode::Record(_) => false,
+      ReplayMode::Playback { replay, cursor } => *cursor >= replay.actions.len(),
+    }
+  }
+
+  /// The recorded actions for `tick`, advancing past them.
+  pub fn playback_actions(&mut self, tick: u32) -> &[ReplayAction] {
+    let ReplayMode::Playback { replay, cursor } = self else {
+      return &[];
+    };
+    let start = *cursor;
+    while replay
+      .actions
+      .get(*cursor)
+      .is_some_and(|action| action.tick <= tick)
+    {
+      *cursor += 1;
+    }
+    &replay.actions[start..*cursor]
+  }
+}
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems((
+      start_playback.on_startup(),
+      save_replay.in_schedule(OnExit(AppState::Game)),
+      save_replay_on_exit
+        .in_base_set(CoreSet::Last)
+        .run_if(in_state(AppState::Game)),
+      // the game's last actions were applied last frame, so a loss has already been seen to
+      end_playback
+        .before(SendActions)
+        .in_set(OnUpdate(AppState::Game)),
+    ));
+  }
+}
+
+/// Plays back the game from an `InputSource::Replay`, if that's the source, instead of showing
+/// the menu.
+fn start_playback(
+  mut commands: Commands,
+  source: Res<InputSource>,
+  mut game_info: ResMut<GameInfo>,
+  mut next_state: ResMut<NextState<AppState>>,
+) {
+  let InputSource::Replay(replay) = &*source else {
+    return;
+  };
+  game_info.players = replay.players;
+  // the CPU's moves are in the replay too
+  game_info.cpu = false;
+  commands.insert_resource(replay.rules);
+  commands.insert_resource(ReplayMode::Playback {
+    replay: replay.clone(),
+    cursor: 0,
+  });
+  next_state.set(AppState::Game);
+}
+
+/// Writes out the game that just ended. A played back game isn't saved again, and the next game
+/// is recorded as usual.
+fn save_replay(mut commands: Commands, replay_mode: Option<Res<ReplayMode>>) {
+  match replay_mode.as_deref() {
+    Some(ReplayMode::Record(replay)) => write_replay(replay),
+    Some(ReplayMode::Playback { .. }) => commands.remove_resource::<ReplayMode>(),
+    None => (),
+  }
+}
+
+/// Saves a game that's still going when the app closes, since it never gets to leave
+/// `AppState::Game`.
+fn save_replay_on_exit(mut exit: EventReader<AppExit>, replay_mode: Option<Res<ReplayMode>>) {
+  if exit.iter().count() == 0 {
+    return;
+  }
+  if let Some(ReplayMode::Record(replay)) = replay_mode.as_deref() {
+    write_replay(replay);
+  }
+}
+
+fn write_replay(replay: &Replay) {
+  let path = replay.new_path();
+  match replay.save(&path) {
+    Ok(()) => info!("Replay saved to {}", path.display()),
+    Err(err) => error!("{err}"),
+  }
+}
+
+/// Heads back to the menu once a replay runs out of actions, as a game that was quit would have.
+fn end_playback(replay_mode: Res<ReplayMode>, mut next_state: ResMut<NextState<AppState>>) {
+  if replay_mode.is_finished() {
+    next_state.set(AppState::Menu);
+  }
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::input::Player;
+
+/// Randomness for a single game. Each player draws from their own streams, and every player's
+/// streams are seeded the same, so both players are dealt the same rows in the same order no
+/// matter how fast either of them plays.
+#[derive(Resource)]
+pub struct GameRng {
+  seed: u64,
+  p1: PlayerRng,
+  p2: PlayerRng,
+}
+
+struct PlayerRng {
+  rows: StdRng,
+  garbage: StdRng,
+  cpu: StdRng,
+}
+
+impl PlayerRng {
+  fn new(seed: u64) -> Self {
+    PlayerRng {
+      rows: StdRng::seed_from_u64(seed),
+      garbage: StdRng::seed_from_u64(seed.wrapping_add(1)),
+      cpu: StdRng::seed_from_u64(seed.wrapping_add(2)),
+    }
+  }
+}
+
+impl GameRng {
+  pub fn new(seed: u64) -> Self {
+    GameRng {
+      seed,
+      p1: PlayerRng::new(seed),
+      p2: PlayerRng::new(seed),
+    }
+  }
+
+  pub fn seed(&self) -> u64 {
+    self.seed
+  }
+
+  /// The stream `player`'s regular rows come from.
+  pub fn player(&mut self, player: Player) -> &mut StdRng {
+    &mut self.player_rng(player).rows
+  }
+
+  /// The stream for garbage rows sent to `player` by their opponent.
+  pub fn garbage(&mut self, player: Player) -> &mut StdRng {
+    &mut self.player_rng(player).garbage
+  }
+
+  /// The stream the CPU playing as `player` rolls its blunders and random moves from.
+  pub fn cpu(&mut self, player: Player) -> &mut StdRng {
+    &mut self.player_rng(player).cpu
+  }
+
+  fn player_rng(&mut self, player: Player) -> &mut PlayerRng {
+    match player {
+      Player::P1 => &mut self.p1,
+      Player::P2 => &mut self.p2,
+    }
+  }
+}
@@ -0,0 +1,34 @@
+//! Reading and writing the RON files the game keeps, like settings, high scores and replays.
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Reads a `what`, e.g. `"replay"`, from `path`. Errors say which file was the problem.
+pub fn load<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T, String> {
+  let text =
+    fs::read_to_string(path).map_err(|err| format!("can't read {}: {err}", path.display()))?;
+  ron::from_str(&text).map_err(|err| format!("invalid {what} {}: {err}", path.display()))
+}
+
+/// Like `load`, but a missing file means the defaults.
+pub fn load_or_default<T: DeserializeOwned + Default>(
+  path: &Path,
+  what: &str,
+) -> Result<T, String> {
+  if !path.exists() {
+    return Ok(T::default());
+  }
+  load(path, what)
+}
+
+/// Writes `value` to `path`, making its directory if needed.
+pub fn save<T: Serialize>(value: &T, path: &Path) -> Result<(), String> {
+  let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+    .map_err(|err| err.to_string())?;
+  if let Some(dir) = path.parent() {
+    fs::create_dir_all(dir).map_err(|err| format!("can't create {}: {err}", dir.display()))?;
+  }
+  fs::write(path, text).map_err(|err| format!("can't write {}: {err}", path.display()))
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{BOARD_DIM, HEIGHT_RANGE, WIDTH_RANGE};
+
+/// Settings that change how a game plays, picked before it starts.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rules {
+  pub board_width: i32,
+  pub board_height: i32,
+}
+
+impl Default for Rules {
+  fn default() -> Self {
+    Rules {
+      board_width: BOARD_DIM.0,
+      board_height: BOARD_DIM.1,
+    }
+  }
+}
+
+impl Rules {
+  pub fn casual() -> Self {
+    Rules {
+      board_width: 6,
+      board_height: 10,
+    }
+  }
+
+  pub fn marathon() -> Self {
+    Rules {
+      board_width: 9,
+      board_height: 14,
+    }
+  }
+
+  /// Parses a preset name (`standard`, `casual`, `marathon`) or a `WIDTHxHEIGHT` board size.
+  pub fn parse(text: &str) -> Result<Self, String> {
+    match text {
+      "standard" => return Ok(Rules::default()),
+      "casual" => return Ok(Rules::casual()),
+      "marathon" => return Ok(Rules::marathon()),
+      _ => (),
+    }
+    let invalid = || format!("invalid board {text:?}, expected a preset or WIDTHxHEIGHT");
+    let (width, height) = text.split_once('x').ok_or_else(invalid)?;
+    let board_width: i32 = width.trim().parse().map_err(|_| invalid())?;
+    let board_height: i32 = height.trim().parse().map_err(|_| invalid())?;
+    let rules = Rules {
+      board_width,
+      board_height,
+    };
+    rules.validate()?;
+    Ok(rules)
+  }
+
+  /// Checks the board fits `WIDTH_RANGE` and `HEIGHT_RANGE`, e.g. for rules read from a file.
+  pub fn validate(&self) -> Result<(), String> {
+    if !WIDTH_RANGE.contains(&self.board_width) || !HEIGHT_RANGE.contains(&self.board_height) {
+      return Err(format!(
+        "board {}x{} is out of range",
+        self.board_width, self.board_height
+      ));
+    }
+    Ok(())
+  }
+
+  /// The rules from `COINGIRL_BOARD`, or the defaults.
+  pub fn from_env() -> Self {
+    let Ok(text) = std::env::var("COINGIRL_BOARD") else {
+      return Rules::default();
+    };
+    Rules::parse(&text).unwrap_or_else(|err| {
+      warn!("{err}");
+      Rules::default()
+    })
+  }
+
+  /// Width over height of the board, in coins.
+  pub fn aspect(&self) -> f32 {
+    self.board_width as f32 / self.board_height as f32
+  }
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::coin_set::Coins;
+use crate::consts::*;
+use crate::game::{EraseEvent, MergeEvent};
+use crate::input::Player;
+use crate::scoring::{erase_points, merge_points};
+use crate::{GameInfo, RenderInfo};
+
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct Score(pub u32);
+
+#[derive(Component)]
+struct ScoreText;
+
+pub struct ScorePlugin;
+impl Plugin for ScorePlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems((
+      setup_score_text.in_schedule(OnEnter(AppState::Game)),
+      add_score.in_set(OnUpdate(AppState::Game)),
+      update_score_text
+        .after(add_score)
+        .in_set(OnUpdate(AppState::Game)),
+      cleanup_score_text.in_schedule(OnExit(AppState::Game)),
+    ));
+  }
+}
+
+fn add_score(
+  mut merge_events: EventReader<MergeEvent>,
+  mut erase_events: EventReader<EraseEvent>,
+  coins: Res<Coins>,
+  mut score_query: Query<(&Player, &mut Score)>,
+) {
+  let merges = merge_events.iter().map(|ev| {
+    (
+      ev.player,
+      merge_points(&coins, ev.obj_type, ev.count, ev.chain),
+    )
+  });
+  let erases = erase_events.iter().map(|ev| {
+    (
+      ev.player,
+      erase_points(&coins, ev.obj_type, ev.count, ev.chain),
+    )
+  });
+  for (player, points) in merges.chain(erases) {
+    for (&score_player, mut score) in &mut score_query {
+      if score_player == player {
+        **score += points;
+      }
+    }
+  }
+}
+
+fn setup_score_text(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  game_info: Res<GameInfo>,
+) {
+  let players = if game_info.players == 2 {
+    vec![Player::P1, Player::P2]
+  } else {
+    vec![Player::P1]
+  };
+  for player in players {
+    commands.spawn((
+      Text2dBundle {
+        text: Text::from_section(
+          "",
+          TextStyle {
+            font: asset_server.load("Evogria.otf"),
+            font_size: 40.0,
+            color: Color::WHITE,
+          },
+        ),
+        text_anchor: Anchor::TopLeft,
+        ..default()
+      },
+      ScoreText,
+      player,
+    ));
+  }
+}
+
+/// Keeps each score to the right of the top of its board.
+fn update_score_text(
+  mut text_query: Query<(&mut Text, &mut Transform, &Player), With<ScoreText>>,
+  score_query: Query<(&Player, Ref<Score>), Without<ScoreText>>,
+  render_info: Res<RenderInfo>,
+) {
+  for (mut text, mut transform, &player) in &mut text_query {
+    let Some((_, score)) = score_query.iter().find(|(&p, _)| p == player) else {
+      continue;
+    };
+    if !score.is_changed() && !render_info.is_changed() {
+      continue;
+    }
+    text.sections[0].value = format!("Score\n{}", **score);
+    text.sections[0].style.font_size = render_info.hud_font_size();
+    transform.translation = render_info.hud_translate(player, 0).extend(UI_Z);
+  }
+}
+
+fn cleanup_score_text(mut commands: Commands, query: Query<Entity, With<ScoreText>>) {
+  for entity in &query {
+    commands.entity(entity).despawn_recursive();
+  }
+}
@@ -0,0 +1,14 @@
+use crate::coins::{CoinSet, ObjType};
+
+/// Points for merging `count` coins of `obj_type`. Every coin past the merge count adds 10%, and
+/// each step of a chain adds the base amount again.
+pub fn merge_points(coins: &CoinSet, obj_type: ObjType, count: usize, chain: u32) -> u32 {
+  let base = coins.value(obj_type) * count as u32;
+  let extra = count.saturating_sub(coins.merge_count(obj_type)) as u32;
+  base * (10 + extra) / 10 * (chain + 1)
+}
+
+/// Points for `count` coins of `obj_type` cleared by an `ItemKind::Erase` item.
+pub fn erase_points(coins: &CoinSet, obj_type: ObjType, count: usize, chain: u32) -> u32 {
+  coins.value(obj_type) * count as u32 * (chain + 1)
+}
@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::Player;
+use crate::menu_ui::Menu;
+use crate::ron_file;
+
+/// Player preferences, kept between sessions.
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+  /// Show where to pull and push in practice games. Ranked games never have hints.
+  pub hints: bool,
+  /// Particles and pops for merges, erases and chains.
+  pub effects: bool,
+  /// Out of `MAX_VOLUME`.
+  pub sfx_volume: u8,
+  /// Out of `MAX_VOLUME`.
+  pub music_volume: u8,
+  /// Out of `MAX_HANDICAP`. A handicap slows down that player's new rows, to even out a versus
+  /// game.
+  pub p1_handicap: u8,
+  /// Out of `MAX_HANDICAP`.
+  pub p2_handicap: u8,
+}
+
+pub const MAX_VOLUME: u8 = 10;
+pub const MAX_HANDICAP: u8 = 4;
+
+impl Default for Settings {
+  fn default() -> Self {
+    Settings {
+      hints: false,
+      effects: true,
+      sfx_volume: 7,
+      music_volume: 5,
+      p1_handicap: 0,
+      p2_handicap: 0,
+    }
+  }
+}
+
+impl Settings {
+  pub fn path() -> PathBuf {
+    crate::data_dir().join("settings.ron")
+  }
+
+  /// Reads the saved settings. A missing file means the defaults.
+  pub fn load() -> Result<Self, String> {
+    ron_file::load_or_default(&Self::path(), "settings")
+  }
+
+  /// How much longer `player` waits for each new row: a quarter more per handicap level.
+  pub fn row_speed_factor(&self, player: Player) -> f32 {
+    let handicap = match player {
+      Player::P1 => self.p1_handicap,
+      Player::P2 => self.p2_handicap,
+    };
+    1.0 + 0.25 * handicap as f32
+  }
+
+  pub fn save(&self) -> Result<(), String> {
+    ron_file::save(self, &Self::path())
+  }
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+  fn build(&self, app: &mut App) {
+    let settings = Settings::load().unwrap_or_else(|err| {
+      warn!("{err}");
+      Settings::default()
+    });
+    app.insert_resource(settings);
+  }
+}
+
+/// The settings submenu. They're saved on the way out.
+pub fn settings_menu(_: &World) -> Menu {
+  Menu::new("Settings")
+    .toggle(
+      "Hints",
+      |world| world.resource::<Settings>().hints,
+      |world, on| world.resource_mut::<Settings>().hints = on,
+    )
+    .toggle(
+      "Effects",
+      |world| world.resource::<Settings>().effects,
+      |world, on| world.resource_mut::<Settings>().effects = on,
+    )
+    .slider(
+      "Sound",
+      MAX_VOLUME,
+      |world| world.resource::<Settings>().sfx_volume,
+      |world, volume| world.resource_mut::<Settings>().sfx_volume = volume,
+    )
+    .slider(
+      "Music",
+      MAX_VOLUME,
+      |world| world.resource::<Settings>().music_volume,
+      |world, volume| world.resource_mut::<Settings>().music_volume = volume,
+    )
+    .slider(
+      "P1 Handicap",
+      MAX_HANDICAP,
+      |world| world.resource::<Settings>().p1_handicap,
+      |world, handicap| world.resource_mut::<Settings>().p1_handicap = handicap,
+    )
+    .slider(
+      "P2 Handicap",
+      MAX_HANDICAP,
+      |world| world.resource::<Settings>().p2_handicap,
+      |world, handicap| world.resource_mut::<Settings>().p2_handicap = handicap,
+    )
+    .back("Back")
+    .on_back(|world| {
+      if let Err(err) = world.resource::<Settings>().save() {
+        error!("{err}");
+      }
+    })
+}
@@ -0,0 +1,398 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::audio::{AddAudioSource, Decodable, Source};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::HashMap;
+
+use crate::consts::{AppState, PauseState};
+use crate::danger::{Danger, DangerEvent};
+use crate::game::{ActionType, GameActionEvent, MergeEvent, PullEvent, PushEvent};
+use crate::settings::{Settings, MAX_VOLUME};
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// The sound of a waveform, in the spirit of old sound chips.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wave {
+  Square,
+  Triangle,
+  /// White noise, changing value at the note's frequency. Lower notes rumble.
+  Noise,
+}
+
+/// A MIDI note number held for `secs`. Note 0 is a rest.
+#[derive(Debug, Clone, Copy)]
+pub struct Note {
+  pub midi: u8,
+  pub secs: f32,
+}
+
+/// A one-voice melody, played by synthesizing it sample by sample, so the game needs no sound
+/// files.
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "5f0b1a5e-8a8f-4d0e-9a53-3c3f1d0d6a21"]
+pub struct Tune {
+  wave: Wave,
+  /// How quickly each note fades, per second. 0 holds notes at full volume.
+  decay: f32,
+  notes: Arc<[Note]>,
+}
+
+impl Tune {
+  pub fn new(wave: Wave, decay: f32, notes: &[(u8, f32)]) -> Self {
+    Tune {
+      wave,
+      decay,
+      notes: notes
+        .iter()
+        .map(|&(midi, secs)| Note { midi, secs })
+        .collect(),
+    }
+  }
+}
+
+impl Decodable for Tune {
+  type DecoderItem = f32;
+  type Decoder = TuneDecoder;
+
+  fn decoder(&self) -> Self::Decoder {
+    TuneDecoder {
+      tune: self.clone(),
+      note: 0,
+      sample: 0,
+      phase: 0.0,
+      noise: 0x2545_f491,
+      level: 0.0,
+    }
+  }
+}
+
+/// Plays a `Tune` one sample at a time.
+pub struct TuneDecoder {
+  tune: Tune,
+  note: usize,
+  /// Samples into the current note.
+  sample: u32,
+  phase: f32,
+  noise: u32,
+  /// The current `Wave::Noise` value.
+  level: f32,
+}
+
+impl Iterator for TuneDecoder {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<f32> {
+    let note = *self.tune.notes.get(self.note)?;
+    let length = (note.secs * SAMPLE_RATE as f32) as u32;
+    if self.sample >= length {
+      self.note += 1;
+      self.sample = 0;
+      return self.next();
+    }
+    let t = self.sample as f32 / SAMPLE_RATE as f32;
+    self.sample += 1;
+    if note.midi == 0 {
+      return Some(0.0);
+    }
+
+    let freq = 440.0 * 2f32.powf((note.midi as f32 - 69.0) / 12.0);
+    self.phase += freq / SAMPLE_RATE as f32;
+    if self.phase >= 1.0 {
+      self.phase -= 1.0;
+      // xorshift
+      self.noise ^= self.noise << 13;
+      self.noise ^= self.noise >> 17;
+      self.noise ^= self.noise << 5;
+      self.level = self.noise as f32 / u32::MAX as f32 * 2.0 - 1.0;
+    }
+    let value = match self.tune.wave {
+      Wave::Square => {
+        if self.phase < 0.5 {
+          1.0
+        } else {
+          -1.0
+        }
+      }
+      Wave::Triangle => 4.0 * (self.phase - 0.5).abs() - 1.0,
+      Wave::Noise => self.level,
+    };
+    // a few milliseconds of fade in and out keep notes from clicking
+    let edge = 0.004;
+    let fade = (t / edge).min((note.secs - t) / edge).clamp(0.0, 1.0);
+    Some(0.25 * value * fade * (-self.tune.decay * t).exp())
+  }
+}
+
+impl Source for TuneDecoder {
+  fn current_frame_len(&self) -> Option<usize> {
+    None
+  }
+
+  fn channels(&self) -> u16 {
+    1
+  }
+
+  fn sample_rate(&self) -> u32 {
+    SAMPLE_RATE
+  }
+
+  fn total_duration(&self) -> Option<Duration> {
+    let secs = self.tune.notes.iter().map(|note| note.secs).sum();
+    Some(Duration::from_secs_f32(secs))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sfx {
+  Move,
+  Pull,
+  Push,
+  Merge,
+  NewRow,
+  Danger,
+  GameOver,
+}
+
+impl Sfx {
+  const ALL: [Sfx; 7] = [
+    Sfx::Move,
+    Sfx::Pull,
+    Sfx::Push,
+    Sfx::Merge,
+    Sfx::NewRow,
+    Sfx::Danger,
+    Sfx::GameOver,
+  ];
+
+  fn tune(self) -> Tune {
+    match self {
+      Sfx::Move => Tune::new(Wave::Triangle, 20.0, &[(84, 0.03)]),
+      Sfx::Pull => Tune::new(Wave::Square, 8.0, &[(60, 0.03), (64, 0.03), (67, 0.05)]),
+      Sfx::Push => Tune::new(Wave::Square, 8.0, &[(67, 0.03), (64, 0.03), (60, 0.05)]),
+      Sfx::Merge => Tune::new(Wave::Square, 6.0, &[(72, 0.05), (76, 0.05), (79, 0.1)]),
+      Sfx::NewRow => Tune::new(Wave::Noise, 25.0, &[(40, 0.12)]),
+      Sfx::Danger => Tune::new(
+        Wave::Square,
+        0.0,
+        &[(81, 0.1), (0, 0.05), (81, 0.1), (0, 0.05), (81, 0.1)],
+      ),
+      Sfx::GameOver => Tune::new(
+        Wave::Triangle,
+        1.0,
+        &[(67, 0.18), (63, 0.18), (60, 0.18), (55, 0.6)],
+      ),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Track {
+  Menu,
+  Game,
+}
+
+impl Track {
+  fn tune(self) -> Tune {
+    // arpeggios over I-vi-IV-V
+    let chords: [[u8; 4]; 4] = [
+      [60, 64, 67, 72],
+      [57, 60, 64, 69],
+      [53, 57, 60, 65],
+      [55, 59, 62, 67],
+    ];
+    let mut notes = Vec::new();
+    match self {
+      Track::Menu => {
+        for chord in chords {
+          for i in [0, 1, 2, 3, 2, 1, 0, 1] {
+            notes.push((chord[i], 0.21));
+          }
+        }
+        Tune::new(Wave::Triangle, 3.0, &notes)
+      }
+      Track::Game => {
+        for chord in [chords[1], chords[2], chords[0], chords[3]] {
+          for i in [0, 2, 1, 3, 0, 2, 1, 3, 0, 2, 1, 3, 3, 2, 1, 0] {
+            notes.push((chord[i] - 12, 0.1));
+          }
+        }
+        Tune::new(Wave::Square, 8.0, &notes)
+      }
+    }
+  }
+}
+
+#[derive(Resource)]
+struct Sounds {
+  sfx: HashMap<Sfx, Handle<Tune>>,
+  menu: Handle<Tune>,
+  game: Handle<Tune>,
+}
+
+impl Sounds {
+  fn track(&self, track: Track) -> Handle<Tune> {
+    match track {
+      Track::Menu => self.menu.clone(),
+      Track::Game => self.game.clone(),
+    }
+  }
+}
+
+/// The music that's on, if any.
+#[derive(Resource, Default)]
+struct Music {
+  track: Option<Track>,
+  sink: Handle<AudioSink>,
+}
+
+pub struct SoundPlugin;
+impl Plugin for SoundPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_audio_source::<Tune>()
+      .init_resource::<Music>()
+      .add_startup_system(setup_sounds)
+      .add_systems((
+        action_sounds.in_set(OnUpdate(AppState::Game)),
+        pull_push_sounds.in_set(OnUpdate(AppState::Game)),
+        merge_sounds.in_set(OnUpdate(AppState::Game)),
+        danger_sounds.in_set(OnUpdate(AppState::Game)),
+        game_over_sound.in_schedule(OnEnter(AppState::Lost)),
+        play_music,
+      ));
+  }
+}
+
+fn setup_sounds(mut commands: Commands, mut tunes: ResMut<Assets<Tune>>) {
+  let sfx = Sfx::ALL
+    .into_iter()
+    .map(|sfx| (sfx, tunes.add(sfx.tune())))
+    .collect();
+  commands.insert_resource(Sounds {
+    sfx,
+    menu: tunes.add(Track::Menu.tune()),
+    game: tunes.add(Track::Game.tune()),
+  });
+}
+
+/// Plays `sfx` at the volume from the settings. `speed` above 1 plays it faster and higher.
+fn play_sfx(audio: &Audio<Tune>, sounds: &Sounds, settings: &Settings, sfx: Sfx, speed: f32) {
+  if settings.sfx_volume == 0 {
+    return;
+  }
+  audio.play_with_settings(
+    sounds.sfx[&sfx].clone(),
+    PlaybackSettings::ONCE
+      .with_volume(settings.sfx_volume as f32 / MAX_VOLUME as f32)
+      .with_speed(speed),
+  );
+}
+
+fn action_sounds(
+  mut events: EventReader<GameActionEvent>,
+  audio: Res<Audio<Tune>>,
+  sounds: Res<Sounds>,
+  settings: Res<Settings>,
+) {
+  for ev in events.iter() {
+    let sfx = match ev.action_type {
+      ActionType::MoveLeft | ActionType::MoveRight => Sfx::Move,
+      ActionType::NewRow => Sfx::NewRow,
+      // these only sound if the board takes them
+      ActionType::CoinPull | ActionType::CoinPush => continue,
+    };
+    play_sfx(&audio, &sounds, &settings, sfx, 1.0);
+  }
+}
+
+fn pull_push_sounds(
+  mut pull_events: EventReader<PullEvent>,
+  mut push_events: EventReader<PushEvent>,
+  audio: Res<Audio<Tune>>,
+  sounds: Res<Sounds>,
+  settings: Res<Settings>,
+) {
+  for _ in pull_events.iter() {
+    play_sfx(&audio, &sounds, &settings, Sfx::Pull, 1.0);
+  }
+  for _ in push_events.iter() {
+    play_sfx(&audio, &sounds, &settings, Sfx::Push, 1.0);
+  }
+}
+
+fn merge_sounds(
+  mut events: EventReader<MergeEvent>,
+  audio: Res<Audio<Tune>>,
+  sounds: Res<Sounds>,
+  settings: Res<Settings>,
+) {
+  for ev in events.iter() {
+    // a whole tone higher for every link of the chain
+    let speed = 2f32.powf(2.0 * ev.chain.min(12) as f32 / 12.0);
+    play_sfx(&audio, &sounds, &settings, Sfx::Merge, speed);
+  }
+}
+
+/// Sounds the alarm when a board gets into danger.
+fn danger_sounds(
+  mut events: EventReader<DangerEvent>,
+  audio: Res<Audio<Tune>>,
+  sounds: Res<Sounds>,
+  settings: Res<Settings>,
+) {
+  if events.iter().any(|ev| ev.started) {
+    play_sfx(&audio, &sounds, &settings, Sfx::Danger, 1.0);
+  }
+}
+
+fn game_over_sound(audio: Res<Audio<Tune>>, sounds: Res<Sounds>, settings: Res<Settings>) {
+  play_sfx(&audio, &sounds, &settings, Sfx::GameOver, 1.0);
+}
+
+/// Keeps the right track looping for the current screen, hurrying it along while anyone is in
+/// danger. The game over jingle gets the lost screen to itself.
+fn play_music(
+  state: Res<State<AppState>>,
+  pause_state: Res<State<PauseState>>,
+  settings: Res<Settings>,
+  danger_query: Query<&Danger>,
+  sounds: Option<Res<Sounds>>,
+  mut music: ResMut<Music>,
+  audio: Res<Audio<Tune>>,
+  sinks: Res<Assets<AudioSink>>,
+) {
+  let Some(sounds) = sounds else {
+    return;
+  };
+  let track = match state.0 {
+    AppState::Game => Some(Track::Game),
+    AppState::Lost => None,
+    _ => Some(Track::Menu),
+  };
+  if track != music.track {
+    if let Some(sink) = sinks.get(&music.sink) {
+      sink.stop();
+    }
+    music.track = track;
+    music.sink = match track {
+      Some(track) => sinks.get_handle(
+        audio.play_with_settings(sounds.track(track), PlaybackSettings::LOOP.with_volume(0.0)),
+      ),
+      None => Handle::default(),
+    };
+  }
+  let Some(sink) = sinks.get(&music.sink) else {
+    return;
+  };
+  sink.set_volume(settings.music_volume as f32 / MAX_VOLUME as f32);
+  let danger = danger_query.iter().any(Danger::is_active);
+  sink.set_speed(if danger { 1.25 } else { 1.0 });
+  if pause_state.0 == PauseState::Paused {
+    sink.pause();
+  } else {
+    sink.play();
+  }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+pub use crate::curve::SpeedCurve;
+
+use crate::consts::*;
+use crate::input::Player;
+use crate::{GameInfo, RenderInfo};
+
+/// Counts down to a player's next new row. Each player has their own, so one player's pacing
+/// can change without touching the other's.
+#[derive(Component)]
+pub struct RowTimer {
+  timer: Timer,
+  elapsed: Duration,
+  curve: SpeedCurve,
+  level: u32,
+  /// Multiplier on every interval, above 1.0 for a handicap.
+  speed_factor: f32,
+}
+
+impl RowTimer {
+  pub fn new(curve: SpeedCurve) -> Self {
+    RowTimer {
+      timer: Timer::new(curve.interval(0), TimerMode::Once),
+      elapsed: Duration::ZERO,
+      curve,
+      level: 0,
+      speed_factor: 1.0,
+    }
+  }
+
+  /// Stretches every interval by `speed_factor`, including the first.
+  pub fn with_speed_factor(mut self, speed_factor: f32) -> Self {
+    self.speed_factor = speed_factor;
+    self.restart();
+    self
+  }
+
+  /// Advances the countdown and returns whether a new row is due. The next interval is taken
+  /// from the level reached by then.
+  pub fn tick(&mut self, delta: Duration, score: u32) -> bool {
+    self.elapsed += delta;
+    self.level = self.curve.level(self.elapsed, score);
+    if !self.timer.tick(delta).just_finished() {
+      return false;
+    }
+    self.restart();
+    true
+  }
+
+  /// Starts a fresh countdown, e.g. after the player forced a new row early.
+  pub fn restart(&mut self) {
+    self
+      .timer
+      .set_duration(self.curve.interval(self.level).mul_f32(self.speed_factor));
+    self.timer.reset();
+  }
+
+  pub fn percent_left(&self) -> f32 {
+    self.timer.percent_left()
+  }
+}
+
+#[derive(Component)]
+struct RowTimerBar;
+
+pub struct SpeedPlugin;
+impl Plugin for SpeedPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems((
+      setup_row_timer_bar.in_schedule(OnEnter(AppState::Game)),
+      update_row_timer_bar.in_set(OnUpdate(AppState::Game)),
+      cleanup_row_timer_bar.in_schedule(OnExit(AppState::Game)),
+    ));
+  }
+}
+
+fn setup_row_timer_bar(mut commands: Commands, game_info: Res<GameInfo>) {
+  let players = if game_info.players == 2 {
+    vec![Player::P1, Player::P2]
+  } else {
+    vec![Player::P1]
+  };
+  for player in players {
+    commands.spawn((
+      SpriteBundle {
+        sprite: Sprite {
+          color: Color::rgb(1.0, 0.8, 0.2),
+          anchor: Anchor::BottomLeft,
+          ..default()
+        },
+        ..default()
+      },
+      RowTimerBar,
+      player,
+    ));
+  }
+}
+
+/// Shrinks a bar along the top edge of each board as the next row gets closer.
+fn update_row_timer_bar(
+  mut bar_query: Query<(&mut Sprite, &mut Transform, &Player), With<RowTimerBar>>,
+  row_timer_query: Query<(&Player, &RowTimer)>,
+  render_info: Res<RenderInfo>,
+) {
+  for (mut sprite, mut transform, &player) in &mut bar_query {
+    let Some((_, row_timer)) = row_timer_query.iter().find(|(&p, _)| p == player) else {
+      continue;
+    };
+    let width = render_info.board_width as f32 * render_info.coin_size;
+    sprite.custom_size = Some(Vec2::new(
+      width * row_timer.percent_left(),
+      render_info.coin_size * 0.1,
+    ));
+    transform.translation = (render_info.obj_translate(player, 0, 0)
+      + Vec2::new(-0.5, 0.5) * render_info.coin_size)
+      .extend(UI_Z);
+  }
+}
+
+fn cleanup_row_timer_bar(mut commands: Commands, query: Query<Entity, With<RowTimerBar>>) {
+  for entity in &query {
+    commands.entity(entity).despawn_recursive();
+  }
+}
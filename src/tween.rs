@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+/// How quickly sprites catch up with the board. Only the sprites take this long; the board they
+/// show has already moved.
+#[derive(Resource, Debug, Clone)]
+pub struct TweenSpeeds {
+  /// Seconds for a pull, a push, a new row or a step of the girl.
+  pub slide_secs: f32,
+  /// How fast falling coins speed up, in cells per second squared.
+  pub fall_gravity: f32,
+}
+
+impl Default for TweenSpeeds {
+  fn default() -> Self {
+    TweenSpeeds {
+      slide_secs: 0.08,
+      fall_gravity: 150.0,
+    }
+  }
+}
+
+/// How a sprite gets to where it's going next.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Motion {
+  /// Quick out of the blocks, easing into place.
+  #[default]
+  Slide,
+  /// Starts still and speeds up, like it's dropping.
+  Fall,
+}
+
+/// Moves a sprite's `Transform` towards a spot over time, instead of jumping there.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Tween {
+  from: Vec2,
+  to: Vec2,
+  elapsed: f32,
+  duration: f32,
+  motion: Motion,
+}
+
+impl Tween {
+  /// Already resting at `position`.
+  pub fn at(position: Vec2) -> Self {
+    Tween {
+      from: position,
+      to: position,
+      elapsed: 0.0,
+      duration: 0.0,
+      motion: Motion::Slide,
+    }
+  }
+
+  /// Where the sprite is headed, or is.
+  pub fn target(&self) -> Vec2 {
+    self.to
+  }
+
+  /// Heads for `to` from wherever the sprite is now. `cell_size` is how big a board cell is on
+  /// screen, which falls are timed by.
+  pub fn retarget(&mut self, to: Vec2, motion: Motion, cell_size: f32, speeds: &TweenSpeeds) {
+    self.from = self.position();
+    self.to = to;
+    self.elapsed = 0.0;
+    self.motion = motion;
+    self.duration = match motion {
+      Motion::Slide => speeds.slide_secs,
+      Motion::Fall => {
+        let cells = self.from.distance(to) / cell_size.max(1.0);
+        (2.0 * cells / speeds.fall_gravity).sqrt()
+      }
+    };
+  }
+
+  pub fn snap(&mut self, to: Vec2) {
+    *self = Tween::at(to);
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.elapsed >= self.duration
+  }
+
+  fn position(&self) -> Vec2 {
+    if self.is_finished() {
+      return self.to;
+    }
+    let t = self.elapsed / self.duration;
+    let eased = match self.motion {
+      Motion::Slide => 1.0 - (1.0 - t) * (1.0 - t),
+      Motion::Fall => t * t,
+    };
+    self.from.lerp(self.to, eased)
+  }
+}
+
+pub struct TweenPlugin;
+impl Plugin for TweenPlugin {
+  fn build(&self, app: &mut App) {
+    app.init_resource::<TweenSpeeds>().add_system(
+      animate
+        .in_base_set(CoreSet::PostUpdate)
+        .before(TransformSystem::TransformPropagate),
+    );
+  }
+}
+
+fn animate(time: Res<Time>, mut query: Query<(&mut Tween, &mut Transform)>) {
+  for (mut tween, mut transform) in &mut query {
+    if tween.is_finished() && transform.translation.truncate() == tween.to {
+      continue;
+    }
+    tween.elapsed += time.delta_seconds();
+    let z = transform.translation.z;
+    transform.translation = tween.position().extend(z);
+  }
+}
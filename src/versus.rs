@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::coin_set::Coins;
+use crate::coins::{CoinSet, ObjType};
+use crate::consts::*;
+use crate::game::{ApplyActions, EraseEvent, MergeEvent};
+use crate::input::Player;
+use crate::{GameInfo, RenderInfo};
+
+/// Garbage rows waiting to land on this player's board along with their next new row.
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct IncomingAttack(pub u32);
+
+#[derive(Component)]
+struct IncomingText;
+
+pub struct VersusPlugin;
+impl Plugin for VersusPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_systems((
+      setup_incoming_text.in_schedule(OnEnter(AppState::Game)),
+      send_attacks
+        .after(ApplyActions)
+        .in_set(OnUpdate(AppState::Game)),
+      update_incoming_text
+        .after(send_attacks)
+        .in_set(OnUpdate(AppState::Game)),
+      cleanup_incoming_text.in_schedule(OnExit(AppState::Game)),
+    ));
+  }
+}
+
+/// Garbage rows sent by clearing `count` coins of `obj_type`: one per chain step, plus one for
+/// every double-sized group.
+pub fn attack_rows(coins: &CoinSet, obj_type: ObjType, count: usize, chain: u32) -> u32 {
+  chain + (count / (2 * coins.merge_count(obj_type))) as u32
+}
+
+/// Turns merges into attacks. An attack first cancels out the attacker's own incoming rows, and
+/// whatever is left over is queued on the opponent.
+fn send_attacks(
+  mut merge_events: EventReader<MergeEvent>,
+  mut erase_events: EventReader<EraseEvent>,
+  coins: Res<Coins>,
+  mut incoming_query: Query<(&Player, &mut IncomingAttack)>,
+  game_info: Res<GameInfo>,
+) {
+  let merges = merge_events.iter().map(|ev| {
+    (
+      ev.player,
+      attack_rows(&coins, ev.obj_type, ev.count, ev.chain),
+    )
+  });
+  let erases = erase_events.iter().map(|ev| {
+    (
+      ev.player,
+      attack_rows(&coins, ev.obj_type, ev.count, ev.chain),
+    )
+  });
+  let attacks: Vec<(Player, u32)> = merges.chain(erases).collect();
+  if game_info.players != 2 {
+    return;
+  }
+  for (attacker, mut rows) in attacks {
+    for (&player, mut incoming) in &mut incoming_query {
+      if player == attacker {
+        let cancelled = rows.min(**incoming);
+        **incoming -= cancelled;
+        rows -= cancelled;
+      }
+    }
+    for (&player, mut incoming) in &mut incoming_query {
+      if player != attacker {
+        **incoming += rows;
+      }
+    }
+  }
+}
+
+fn setup_incoming_text(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  game_info: Res<GameInfo>,
+) {
+  if game_info.players != 2 {
+    return;
+  }
+  for player in [Player::P1, Player::P2] {
+    commands.spawn((
+      Text2dBundle {
+        text: Text::from_section(
+          "",
+          TextStyle {
+            font: asset_server.load("Evogria.otf"),
+            font_size: 40.0,
+            color: Color::rgb(1.0, 0.3, 0.3),
+          },
+        ),
+        text_anchor: Anchor::TopLeft,
+        ..default()
+      },
+      IncomingText,
+      player,
+    ));
+  }
+}
+
+/// Shows the queued garbage under the score, to the right of each board.
+fn update_incoming_text(
+  mut text_query: Query<(&mut Text, &mut Transform, &Player), With<IncomingText>>,
+  incoming_query: Query<(&Player, Ref<IncomingAttack>), Without<IncomingText>>,
+  render_info: Res<RenderInfo>,
+) {
+  for (mut text, mut transform, &player) in &mut text_query {
+    let Some((_, incoming)) = incoming_query.iter().find(|(&p, _)| p == player) else {
+      continue;
+    };
+    if !incoming.is_changed() && !render_info.is_changed() {
+      continue;
+    }
+    text.sections[0].value = if **incoming > 0 {
+      format!("Incoming\n{}", **incoming)
+    } else {
+      String::new()
+    };
+    text.sections[0].style.font_size = render_info.hud_font_size();
+    transform.translation = render_info.hud_translate(player, 2).extend(UI_Z);
+  }
+}
+
+fn cleanup_incoming_text(mut commands: Commands, query: Query<Entity, With<IncomingText>>) {
+  for entity in &query {
+    commands.entity(entity).despawn_recursive();
+  }
+}